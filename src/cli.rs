@@ -1,22 +1,35 @@
-use std::{env, path::PathBuf};
+use std::{env, num::NonZeroUsize, path::PathBuf};
 
-use clap::{Parser, ValueEnum};
+use chrono::NaiveDate;
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
 use strfmt::strfmt;
-use sunny::utils::format_container;
+use sunny::{
+    models::{self, Album, SearchKind, Track},
+    overrides::Overrides,
+    utils::{self, format_container, TagProfile},
+};
 
 #[derive(Debug, Parser)]
 #[clap(
     about,
     version,
-    after_help = "Note: run --help to see full descriptions of each flags/options"
+    after_help = "Note: run --help to see full descriptions of each flags/options",
+    subcommand_negates_reqs = true,
+    args_conflicts_with_subcommands = true,
+    group = ArgGroup::new("listing").args(["search", "list_available"]).multiple(true)
 )]
 pub struct Config {
     /// Artist's bandcamp username or full url
-    #[clap(display_order = 1, value_parser, value_name = "ARTIST | URL")]
-    pub(crate) url: String,
+    #[clap(
+        display_order = 1,
+        value_parser,
+        value_name = "ARTIST | URL",
+        required = true
+    )]
+    pub(crate) url: Option<String>,
 
     /// Directory path where downloads should be saved to
-    #[clap(short, long, display_order = 2, value_parser = validate_path, long_help = r"Directory path where downloads should be saved to.
+    #[clap(short, long, global = true, display_order = 2, value_parser = validate_path, long_help = r"Directory path where downloads should be saved to.
 By default files are saved in the current directory.")]
     pub(crate) path: Option<PathBuf>,
 
@@ -24,6 +37,7 @@ By default files are saved in the current directory.")]
     #[clap(
         short,
         long,
+        global = true,
         value_parser = validate_format,
         value_name = "FORMAT",
         long_help = r"Specify track format: default is '{num} - {track}'
@@ -31,8 +45,12 @@ By default files are saved in the current directory.")]
 available keys:
     {num} - track number
     {track} - track
-    {artist} - artist
+    {artist} - album artist
+    {track_artist} - track artist, differs from album artist on compilations
     {album} - album
+    {label} - label
+    {catalog} - catalog number
+    {release_type} - album, ep, single or broadcast
 
 usage:
     -t='{num} - {track} - {album} {artist}'
@@ -48,6 +66,62 @@ note that `.mp3` is appended automatically.")]
     #[clap(short = 'S', long, value_name = "ALBUMS", value_delimiter = ',')]
     pub(crate) skip_albums: Option<Vec<String>>,
 
+    /// Only download these kinds of releases, note that kinds need to be delimited by ','
+    /// eg: --release-type=album,ep, releases of unknown kind are always downloaded
+    #[clap(long, value_name = "KINDS", value_delimiter = ',')]
+    #[arg(value_enum)]
+    pub(crate) release_type: Option<Vec<ReleaseType>>,
+
+    /// Only download albums released on or after this date, eg: 2020-01-31
+    #[clap(long, value_name = "DATE", value_parser = validate_date)]
+    pub(crate) since: Option<NaiveDate>,
+
+    /// Only download albums released on or before this date, eg: 2020-12-31
+    #[clap(long, value_name = "DATE", value_parser = validate_date)]
+    pub(crate) until: Option<NaiveDate>,
+
+    /// Download albums from the newest or the oldest release,
+    /// instead of the order they are listed in on bandcamp
+    #[clap(long, value_name = "ORDER")]
+    #[arg(value_enum)]
+    pub(crate) order: Option<Order>,
+
+    /// What to do when a track already exists on disk
+    #[clap(long, value_name = "POLICY", default_value_t = OnExists::Skip, long_help = r"What to do when a track already exists on disk.
+
+    skip - leave the existing file alone
+    overwrite - download again and replace the existing file
+    rename - download to the next free name, eg: '1 - Track (2).mp3'
+    verify - download again only if the existing file looks incomplete,
+             or its title/album/track number tags do not match")]
+    #[arg(value_enum)]
+    pub(crate) on_exists: OnExists,
+
+    /// JSON file of metadata replacing what is scraped from bandcamp
+    #[clap(
+        long,
+        global = true,
+        value_name = "FILE",
+        value_parser = load_overrides,
+        long_help = r#"JSON file of metadata replacing what is scraped from bandcamp,
+keyed by album url or ID. Overrides apply to paths and tags alike.
+
+eg:
+    {
+      "https://artist.bandcamp.com/album/album": {
+        "album": "Album",
+        "artist": "Artist",
+        "genres": ["ambient", "drone"],
+        "date": "2020-01-31",
+        "tracks": { "2": { "name": "Track", "artist": "Someone" } }
+      }
+    }"#
+    )]
+    pub(crate) overrides: Option<Overrides>,
+
+    #[clap(flatten)]
+    pub(crate) tagging: TagOptions,
+
     /// List albums/tracks available for download
     #[clap(short, long)]
     pub(crate) list_available: bool,
@@ -61,9 +135,180 @@ note that `.mp3` is appended automatically.")]
     #[arg(value_enum)]
     pub(crate) r#type: SearchType,
 
+    /// Keep bandcamp's order of relevance instead of sorting search results by name
+    #[clap(long, requires = "search")]
+    pub(crate) relevance: bool,
+
+    /// Print search results or available albums as a table or in a machine-readable format
+    #[clap(long, value_name = "FORMAT", default_value_t = Output::Table, requires = "listing")]
+    #[arg(value_enum)]
+    pub(crate) output: Output,
+
+    /// Download the Nth search result, as numbered by `--select`
+    #[clap(long, value_name = "N", requires = "search")]
+    pub(crate) pick: Option<NonZeroUsize>,
+
+    /// Number search results and ask which one to download
+    #[clap(long, requires = "search", conflicts_with_all = ["pick", "output"])]
+    pub(crate) select: bool,
+
     /// Do not do anything; just show what would happen
     #[clap(display_order = 1000, long)]
     pub(crate) dry_run: bool,
+
+    #[clap(subcommand)]
+    pub(crate) command: Option<Command>,
+}
+
+#[derive(Args, Debug)]
+pub struct TagOptions {
+    /// ID3v2 version to write, some older players can only read 2.3
+    #[clap(long, global = true, value_name = "VERSION", default_value_t = Id3Version::V24)]
+    #[arg(value_enum)]
+    pub(crate) id3_version: Id3Version,
+
+    /// Also write an ID3v1 tag, for players that can't read ID3v2 at all
+    #[clap(long, global = true)]
+    pub(crate) id3v1: bool,
+
+    /// Do not write these frames, note that frames need to be delimited by ','
+    /// eg: --no-frames=lyrics,comments
+    #[clap(long, global = true, value_name = "FRAMES", value_delimiter = ',')]
+    #[arg(value_enum)]
+    pub(crate) no_frames: Vec<Frame>,
+
+    /// ISO 639-2 code of the lyrics' language, eg: eng, deu, jpn
+    #[clap(long, global = true, value_name = "CODE", default_value = "eng", value_parser = validate_lang)]
+    pub(crate) lyrics_lang: String,
+
+    /// Largest width/height of the embedded album art in pixels
+    #[clap(
+        long,
+        global = true,
+        value_name = "PIXELS",
+        long_help = r"Largest width/height of the embedded album art in pixels.
+Bandcamp serves album art in fixed sizes (100 to 1200 pixels),
+the largest one that fits is embedded."
+    )]
+    pub(crate) max_art_size: Option<u32>,
+
+    /// Analyze downloaded tracks and tag them with their track & album ReplayGain
+    #[clap(long)]
+    pub(crate) replay_gain: bool,
+
+    /// Also write lyrics to a `.txt` or `.lrc` file next to each track
+    #[clap(long, global = true, value_name = "FORMAT")]
+    #[arg(value_enum)]
+    pub(crate) lyrics_file: Option<LyricsFile>,
+
+    /// Write only the first or all of the album's keywords as genres
+    #[clap(long, global = true, value_name = "STRATEGY", default_value_t = Genre::All)]
+    #[arg(value_enum)]
+    pub(crate) genre: Genre,
+
+    /// Only write keywords listed in FILE as genres
+    #[clap(
+        long,
+        global = true,
+        value_name = "FILE",
+        value_parser = load_genre_map,
+        long_help = r"Only write keywords listed in FILE as genres, one per line.
+A keyword can be renamed with `keyword = Genre`, lines starting with `#` are ignored.
+
+eg:
+    # genres to keep
+    ambient
+    drone = Drone
+    hip hop = Hip-Hop"
+    )]
+    pub(crate) genre_map: Option<utils::GenreMap>,
+}
+
+impl TagOptions {
+    pub(crate) fn as_profile(&self) -> TagProfile {
+        TagProfile {
+            version: match self.id3_version {
+                Id3Version::V23 => id3::Version::Id3v23,
+                Id3Version::V24 => id3::Version::Id3v24,
+            },
+            id3v1: self.id3v1,
+            lyrics: !self.no_frames.contains(&Frame::Lyrics),
+            lyrics_lang: self.lyrics_lang.clone(),
+            pictures: !self.no_frames.contains(&Frame::Pictures),
+            comments: !self.no_frames.contains(&Frame::Comments),
+            max_art_size: self.max_art_size,
+            lyrics_file: self.lyrics_file.as_ref().map(|format| match format {
+                LyricsFile::Txt => utils::LyricsFile::Txt,
+                LyricsFile::Lrc => utils::LyricsFile::Lrc,
+            }),
+            replay_gain: self.replay_gain,
+            genre: match self.genre {
+                Genre::First => utils::Genre::First,
+                Genre::All => utils::Genre::All,
+            },
+            genre_map: self.genre_map.clone(),
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum Id3Version {
+    #[value(name = "2.3")]
+    V23,
+    #[value(name = "2.4")]
+    V24,
+}
+
+#[derive(ValueEnum, Clone, Debug, PartialEq, Eq)]
+pub enum Frame {
+    Lyrics,
+    Pictures,
+    Comments,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum LyricsFile {
+    Txt,
+    Lrc,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum Genre {
+    First,
+    All,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Rewrite tags of already downloaded tracks, without downloading them again
+    ///
+    /// Tracks are looked up with the same `--path` & `--track-format` they were downloaded with,
+    /// frames not written by sunny (eg: ratings or custom comments) are preserved.
+    Retag {
+        /// Artist's bandcamp username or full url
+        #[clap(value_parser, value_name = "ARTIST | URL")]
+        url: String,
+    },
+
+    /// Move already downloaded tracks to match the current `--track-format`
+    ///
+    /// Paths are computed from the ID3 tags of every mp3 file under `--path`,
+    /// directories left empty are removed afterwards.
+    Reorganize {
+        /// Do not move anything; just show what would be moved
+        #[clap(long)]
+        dry_run: bool,
+    },
+
+    /// Check that already downloaded tracks are complete, well-formed mp3 files
+    ///
+    /// Every mp3 file under DIR is checked the same way downloads are: its MPEG frames
+    /// must follow each other up to the end of the file, and add up to its duration tag.
+    Verify {
+        /// Directory to check
+        #[clap(value_parser = validate_path)]
+        dir: PathBuf,
+    },
 }
 
 #[derive(ValueEnum, Clone, Debug)]
@@ -84,6 +329,72 @@ impl SearchType {
             Self::Tracks => "t",
         }
     }
+
+    /// Whether results of `kind` were asked for, bandcamp can't tell artists & labels apart itself.
+    pub(crate) const fn matches(&self, kind: SearchKind) -> bool {
+        matches!(
+            (self, kind),
+            (Self::All, _)
+                | (Self::Artists, SearchKind::Artist)
+                | (Self::Labels, SearchKind::Label)
+                | (Self::Albums, SearchKind::Album)
+                | (Self::Tracks, SearchKind::Track)
+        )
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Output {
+    Table,
+    Json,
+    Ndjson,
+    Yaml,
+    Csv,
+    Tsv,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum ReleaseType {
+    Album,
+    Ep,
+    Single,
+    Broadcast,
+}
+
+impl ReleaseType {
+    pub(crate) const fn as_release_type(&self) -> models::ReleaseType {
+        match self {
+            Self::Album => models::ReleaseType::Album,
+            Self::Ep => models::ReleaseType::Ep,
+            Self::Single => models::ReleaseType::Single,
+            Self::Broadcast => models::ReleaseType::Broadcast,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum Order {
+    Newest,
+    Oldest,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum OnExists {
+    Skip,
+    Overwrite,
+    Rename,
+    Verify,
+}
+
+impl OnExists {
+    pub(crate) const fn as_policy(&self) -> utils::OnExists {
+        match self {
+            Self::Skip => utils::OnExists::Skip,
+            Self::Overwrite => utils::OnExists::Overwrite,
+            Self::Rename => utils::OnExists::Rename,
+            Self::Verify => utils::OnExists::Verify,
+        }
+    }
 }
 
 impl Default for Config {
@@ -93,11 +404,36 @@ impl Default for Config {
 }
 
 fn validate_format(f: &str) -> Result<String, String> {
-    let vars = format_container("", "", "", "");
+    let vars = format_container(&Album::default(), &Track::default());
 
     strfmt(f, &vars).map_err(|err| err.to_string())
 }
 
+fn validate_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()
+        .or_else(|| utils::parse_date(date).map(|date| date.date()))
+        .ok_or_else(|| "expected a date like 2020-01-31".into())
+}
+
+fn load_overrides(path: &str) -> Result<Overrides, String> {
+    Overrides::load(&expand_tilde(path)).map_err(|e| format!("{e:#}"))
+}
+
+fn load_genre_map(path: &str) -> Result<utils::GenreMap, String> {
+    let text = std::fs::read_to_string(expand_tilde(path)).map_err(|e| e.to_string())?;
+
+    utils::GenreMap::parse(&text).map_err(|e| e.to_string())
+}
+
+fn validate_lang(lang: &str) -> Result<String, String> {
+    if lang.len() == 3 && lang.chars().all(|c| c.is_ascii_lowercase()) {
+        Ok(lang.to_string())
+    } else {
+        Err("expected a 3 letter lowercase ISO 639-2 code, eg: eng".into())
+    }
+}
+
 pub fn expand_tilde(p: &str) -> PathBuf {
     #[allow(deprecated)]
     let home = env::home_dir().expect("home_dir to exist");
@@ -3,9 +3,12 @@ use std::env::consts;
 use anyhow::Result;
 use curl::{easy, Version};
 
+#[cfg(feature = "tagging")]
 #[path = "./multi_dl.rs"]
 mod multi_dl;
 
+/// Downloads are tagged as they are written, the downloader needs the `tagging` feature.
+#[cfg(feature = "tagging")]
 pub use multi_dl::Downloader as MultiDownloader;
 
 #[must_use]
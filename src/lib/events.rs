@@ -0,0 +1,69 @@
+use std::path::Path;
+
+use crate::models::{Album, Track};
+
+/// Callback receiving [`Event`]s, called from the thread doing the work.
+pub type OnEvent = dyn Fn(&Event) + Send + Sync;
+
+/// Why a track is not downloaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Skip {
+    /// Bandcamp doesn't stream the track, eg: it has to be bought
+    NoStreamUrl,
+    /// The track is already on disk, see [`crate::utils::OnExists`]
+    Exists,
+    /// The track is already on disk and looks complete, see [`crate::utils::OnExists::Verify`]
+    Intact,
+}
+
+/// Progress of fetching & downloading, as reported to [`crate::SunnyBuilder::on_event`].
+///
+/// Downloads are identified by their position in the download queue, `id`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Event<'a> {
+    /// Albums are being fetched from `url`
+    FetchStarted { url: &'a str },
+    /// `albums` albums were fetched
+    FetchFinished { albums: usize },
+    /// Fetching albums failed with `error`
+    FetchFailed { error: &'a anyhow::Error },
+    /// A track won't be downloaded
+    Skipped {
+        album: &'a Album,
+        track: &'a Track,
+        reason: Skip,
+    },
+    /// A track was queued for download to `path`
+    Queued {
+        id: usize,
+        album: &'a Album,
+        track: &'a Track,
+        path: &'a Path,
+    },
+    /// `downloaded` of `total` bytes of a track were received, `total` is 0 until known
+    Progress {
+        id: usize,
+        downloaded: u64,
+        total: u64,
+    },
+    /// A download failed and is started again
+    Retrying {
+        id: usize,
+        attempt: u32,
+        error: &'a anyhow::Error,
+    },
+    /// A track is downloaded and being written & tagged
+    Tagging { id: usize },
+    /// A track is written & tagged
+    Completed { id: usize },
+    /// A track could not be downloaded or written
+    Failed { id: usize, error: &'a anyhow::Error },
+    /// A track is written, but its ReplayGain analysis failed, reported before it is completed
+    ReplayGainFailed { id: usize, error: &'a anyhow::Error },
+    /// Tracks of the album in `directory` are done, but tagging their album gain failed
+    AlbumGainFailed {
+        directory: &'a Path,
+        error: &'a anyhow::Error,
+    },
+}
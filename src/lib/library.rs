@@ -0,0 +1,290 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Result;
+use id3::{Tag, TagLike};
+
+use crate::{
+    client,
+    models::{Album, ReleaseType, Track},
+    mp3,
+    utils::{album_directory, make_path, tag_mp3, write_lyrics_file, LyricsFile, TagProfile},
+};
+
+/// Rewrite tags of `album`'s tracks that were previously downloaded to `root`, without
+/// touching their audio data.
+///
+/// Tracks are located with the same logic as downloads, ie: [`make_path`],
+/// lyrics files are (re)written too when enabled in `profile`.
+/// Each track is returned along with the path it was retagged at, or `None` if it is not on disk.
+pub fn retag<'a>(
+    album: &'a Album,
+    root: &Path,
+    track_format: &str,
+    profile: &TagProfile,
+) -> Vec<(&'a Track, Result<Option<PathBuf>>)> {
+    let album_art = album
+        .album_art_url
+        .as_ref()
+        .and_then(|url| profile.art_url(url))
+        .and_then(|url| client::get(&url).ok());
+
+    album
+        .tracks
+        .iter()
+        .map(|track| {
+            let path = make_path(album, track, root, track_format);
+
+            if !path.exists() {
+                return (track, Ok(None));
+            }
+
+            let ret = tag_mp3(album_art.clone(), album, track, &path, profile)
+                .and_then(|()| write_lyrics_file(album, track, &path, profile))
+                .map(|_| Some(path));
+
+            (track, ret)
+        })
+        .collect()
+}
+
+/// A file move planned by [`plan_reorganize`].
+#[derive(Debug, Clone)]
+pub struct Move {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    /// Why the file can not be moved, if it can't.
+    pub conflict: Option<String>,
+}
+
+/// All `.mp3` files under `root`, recursively.
+pub fn mp3_files(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            files.extend(mp3_files(&path)?);
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("mp3"))
+        {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+
+    Ok(files)
+}
+
+/// Build a [`Track`] & its [`Album`] back from the ID3 tag of a downloaded file.
+fn track_from_tag(path: &Path) -> Result<(Album, Track), String> {
+    let tag = Tag::read_from_path(path).map_err(|_| "no ID3 tag".to_string())?;
+
+    let field = |value: Option<&str>, name: &str| {
+        value
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+            .ok_or_else(|| format!("missing {name} tag"))
+    };
+
+    let artist = field(tag.album_artist().or(tag.artist()), "artist")?;
+
+    let track = Track {
+        num: tag.track().ok_or("missing track number tag")? as i32,
+        name: field(tag.title(), "title")?,
+        artist: tag
+            .artist()
+            .filter(|track_artist| !track_artist.is_empty() && *track_artist != artist)
+            .map(str::to_string),
+        ..Default::default()
+    };
+
+    let extended_text = |description: &str| {
+        tag.extended_texts()
+            .find(|text| text.description == description)
+            .map(|text| text.value.clone())
+    };
+
+    // every key of the track templates must be read back, see `format_container`
+    let album = Album {
+        artist,
+        album: field(tag.album(), "album")?,
+        label: tag
+            .get("TPUB")
+            .and_then(|frame| frame.content().text())
+            .map(str::to_string),
+        catalog: extended_text("CATALOGNUMBER"),
+        release_type: extended_text("RELEASETYPE")
+            .as_deref()
+            .and_then(ReleaseType::from_name),
+        ..Default::default()
+    };
+
+    Ok((album, track))
+}
+
+/// Plan moving every `.mp3` file under `root` to the path it would be downloaded to with
+/// `track_format`, computed from its ID3 tags. Files already in place are left out.
+///
+/// Moves conflict when the destination already exists, when several files share the same
+/// destination, or when the file lacks the tags needed to compute it.
+pub fn plan_reorganize(root: &Path, track_format: &str) -> Result<Vec<Move>> {
+    let root_buf = root.to_path_buf();
+
+    let mut moves = mp3_files(root)?
+        .into_iter()
+        .filter_map(|from| match track_from_tag(&from) {
+            Ok((album, track)) => {
+                let dir = album_directory(Some(&root_buf), &album);
+                let to = make_path(&album, &track, &dir, track_format);
+
+                if to == from {
+                    None
+                } else {
+                    // lyrics files are moved along, they must not overwrite anything either
+                    let conflict = std::iter::once(to.clone())
+                        .chain(
+                            [LyricsFile::Txt, LyricsFile::Lrc]
+                                .into_iter()
+                                .filter(|format| format.path(&from).exists())
+                                .map(|format| format.path(&to)),
+                        )
+                        .find(|path| path.exists())
+                        .map(|path| format!("`{}` already exists", path.display()));
+
+                    Some(Move { from, to, conflict })
+                }
+            }
+            Err(reason) => Some(Move {
+                to: from.clone(),
+                from,
+                conflict: Some(reason),
+            }),
+        })
+        .collect::<Vec<_>>();
+
+    let mut destinations = HashMap::<PathBuf, usize>::new();
+
+    for item in moves.iter().filter(|item| item.conflict.is_none()) {
+        *destinations.entry(item.to.clone()).or_default() += 1;
+    }
+
+    for item in &mut moves {
+        if item.conflict.is_none() && destinations[&item.to] > 1 {
+            item.conflict = Some(format!(
+                "`{}` is the destination of several files",
+                item.to.display()
+            ));
+        }
+    }
+
+    Ok(moves)
+}
+
+/// A move applied by [`reorganize`].
+#[derive(Debug)]
+pub struct Moved<'a> {
+    pub item: &'a Move,
+    /// Result of moving the track itself
+    pub track: Result<()>,
+    /// Lyrics files next to the track that could not be moved along with it, and why
+    pub lyrics: Vec<(PathBuf, anyhow::Error)>,
+}
+
+/// Apply moves planned by [`plan_reorganize`] skipping conflicting ones,
+/// then remove directories under `root` left empty.
+///
+/// Lyrics files next to the tracks are moved along with them, unless that would
+/// overwrite an existing file.
+pub fn reorganize<'a>(moves: &'a [Move], root: &Path) -> Vec<Moved<'a>> {
+    let applied = moves
+        .iter()
+        .filter(|item| item.conflict.is_none())
+        .map(|item| {
+            // the destination may have appeared since the move was planned
+            let track = if item.to.exists() {
+                Err(anyhow::anyhow!("`{}` already exists", item.to.display()))
+            } else {
+                item.to
+                    .parent()
+                    .map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|()| fs::rename(&item.from, &item.to))
+                    .map_err(Into::into)
+            };
+
+            let lyrics = if track.is_ok() {
+                move_lyrics_files(&item.from, &item.to)
+            } else {
+                vec![]
+            };
+
+            Moved {
+                item,
+                track,
+                lyrics,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    for moved in &applied {
+        remove_empty_parents(&moved.item.from, root);
+    }
+
+    applied
+}
+
+/// Move lyrics files of the track moved `from` -> `to`, returning failed ones.
+fn move_lyrics_files(from: &Path, to: &Path) -> Vec<(PathBuf, anyhow::Error)> {
+    [LyricsFile::Txt, LyricsFile::Lrc]
+        .into_iter()
+        .map(|format| (format.path(from), format.path(to)))
+        .filter(|(lyrics, _)| lyrics.exists())
+        .filter_map(|(lyrics, dest)| {
+            let error = if dest.exists() {
+                anyhow::anyhow!("`{}` already exists", dest.display())
+            } else {
+                fs::rename(&lyrics, &dest).err()?.into()
+            };
+
+            Some((lyrics, error))
+        })
+        .collect()
+}
+
+/// Remove parent directories of `path` as long as they are empty, stopping at `root`.
+fn remove_empty_parents(path: &Path, root: &Path) {
+    for dir in path.ancestors().skip(1) {
+        if dir == root || !dir.starts_with(root) || fs::remove_dir(dir).is_err() {
+            break;
+        }
+    }
+}
+
+/// Check every `.mp3` file under `root` like downloads are: its MPEG stream must be
+/// well-formed and as long as its `TLEN` tag, when it has one.
+pub fn verify(root: &Path) -> Result<Vec<(PathBuf, Result<mp3::Stream>)>> {
+    let files = mp3_files(root)?
+        .into_iter()
+        .map(|path| {
+            let expected = Tag::read_from_path(&path)
+                .ok()
+                .and_then(|tag| tag.duration())
+                .map(|ms| Duration::from_millis(ms.into()));
+
+            let ret = fs::read(&path)
+                .map_err(Into::into)
+                .and_then(|data| mp3::verify(&data, expected));
+
+            (path, ret)
+        })
+        .collect();
+
+    Ok(files)
+}
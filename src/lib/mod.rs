@@ -9,5 +9,26 @@ pub mod spider;
 /// Miscellaneous small utilities for mostly internal usage
 pub mod utils;
 
+/// Checks of MPEG audio streams
+pub mod mp3;
+
+/// ReplayGain analysis of mp3 files
+#[cfg(feature = "tagging")]
+pub mod replaygain;
+
 /// Client to download single or multiple items
 pub mod client;
+
+/// Operations on already downloaded tracks
+#[cfg(feature = "tagging")]
+pub mod library;
+
+/// User provided metadata replacing scraped one
+pub mod overrides;
+
+/// Progress of fetching & downloading, reported to the library's user
+pub mod events;
+
+mod sunny;
+
+pub use self::sunny::{Sunny, SunnyBuilder};
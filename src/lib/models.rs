@@ -1,13 +1,55 @@
+use std::{fmt, time::Duration};
+
+use chrono::NaiveDateTime;
+
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Track {
     pub num: i32,
     pub name: String,
     pub url: String,
     pub lyrics: Option<String>,
-    pub album: Album,
+    #[cfg_attr(feature = "serde", serde(with = "secs"))]
+    pub duration: Option<Duration>,
+    /// Artist of the track when it differs from the album's, eg: on compilations
+    pub artist: Option<String>,
+    /// Url of the track's page on bandcamp
+    pub page_url: Option<String>,
+    /// Bandcamp's ID of the track
+    pub id: Option<u64>,
+    /// Bandcamp's ID of the album the track is part of, see [`Track::album`]
+    pub album_id: Option<u64>,
 }
 
 impl Track {
+    /// The album of `albums` this track is part of: the one holding it, or else the one
+    /// with its [`Track::album_id`], eg: for a copy of the track.
+    #[must_use]
+    pub fn album<'a>(&self, albums: &'a [Album]) -> Option<&'a Album> {
+        albums
+            .iter()
+            .find(|album| album.tracks.iter().any(|track| std::ptr::eq(track, self)))
+            .or_else(|| {
+                let id = self.album_id?;
+                albums.iter().find(|album| album.id == Some(id))
+            })
+    }
+
+    /// Other tracks of the album this track is part of, see [`Track::album`].
+    pub fn siblings<'a>(&'a self, albums: &'a [Album]) -> impl Iterator<Item = &'a Track> {
+        self.album(albums)
+            .into_iter()
+            .flat_map(|album| &album.tracks)
+            .filter(move |track| track.num != self.num)
+    }
+
+    /// Artist of the track, which is the artist of its `album` unless set otherwise.
+    #[must_use]
+    pub fn artist_name<'a>(&'a self, album: &'a Album) -> &'a str {
+        self.artist.as_deref().unwrap_or(&album.artist)
+    }
+
     #[must_use]
     pub fn missing_fields(&self) -> Vec<String> {
         let mut missing = vec![];
@@ -39,17 +81,105 @@ impl Track {
 }
 
 #[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Album {
     pub artist: String,
     pub album: String,
+    /// Release date as found on the page
     pub release_date: String,
+    /// `release_date` parsed, `None` if it's in a format we don't know of
+    pub released: Option<NaiveDateTime>,
     pub tracks: Vec<Track>,
-    pub tags: Option<String>,
+    /// Bandcamp keywords of the album, genres & moods mostly
+    pub tags: Vec<String>,
+    /// Where the artist is based, eg: `Portland, Oregon`
+    pub location: Option<String>,
     pub album_art_url: Option<String>,
     pub artist_art_url: Option<String>,
+    /// Url of the album's page on bandcamp
+    pub url: String,
+    /// Bandcamp's ID of the album
+    pub id: Option<u64>,
+    /// Album's "about" text
+    pub about: Option<String>,
+    /// Album's credits text
+    pub credits: Option<String>,
+    /// Label or publisher of the album
+    pub label: Option<String>,
+    /// Total number of tracks, including the ones that can't be downloaded
+    pub num_tracks: u32,
+    /// Catalog number given by the label
+    pub catalog: Option<String>,
+    pub release_type: Option<ReleaseType>,
+}
+
+/// Kind of release of an album, as per schema.org's `MusicAlbumReleaseType`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ReleaseType {
+    Album,
+    Ep,
+    Single,
+    Broadcast,
+}
+
+impl ReleaseType {
+    /// Parse schema.org's names, eg: `AlbumRelease` or `http://schema.org/EPRelease`.
+    #[must_use]
+    pub fn from_schema(name: &str) -> Option<Self> {
+        match name.rsplit('/').next()? {
+            "AlbumRelease" => Some(Self::Album),
+            "EPRelease" => Some(Self::Ep),
+            "SingleRelease" => Some(Self::Single),
+            "BroadcastRelease" => Some(Self::Broadcast),
+            _ => None,
+        }
+    }
+
+    /// Parse names as displayed, eg: `ep`.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "album" => Some(Self::Album),
+            "ep" => Some(Self::Ep),
+            "single" => Some(Self::Single),
+            "broadcast" => Some(Self::Broadcast),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ReleaseType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Album => "album",
+            Self::Ep => "ep",
+            Self::Single => "single",
+            Self::Broadcast => "broadcast",
+        })
+    }
 }
 
 impl Album {
+    /// Point the album's tracks at it through [`Track::album_id`], eg: after building it
+    /// or changing its ID.
+    pub fn link_tracks(&mut self) {
+        for track in &mut self.tracks {
+            track.album_id = self.id;
+        }
+    }
+
+    /// Total duration of the album's tracks, `None` if none of them has a duration.
+    #[must_use]
+    pub fn duration(&self) -> Option<Duration> {
+        self.tracks
+            .iter()
+            .filter_map(|track| track.duration)
+            .reduce(|a, b| a + b)
+    }
+
     pub fn update(&mut self, other: Self) {
         if !self.album.is_empty() {
             self.album = other.album;
@@ -63,14 +193,20 @@ impl Album {
             self.release_date = other.release_date;
         }
 
+        if self.released.is_none() {
+            self.released = other.released;
+        }
+
         if !self.tracks.is_empty() {
             self.tracks = other.tracks;
         }
 
-        if let Some(ref tags) = self.tags {
-            if tags.is_empty() {
-                self.tags = other.tags;
-            }
+        if self.tags.is_empty() {
+            self.tags = other.tags;
+        }
+
+        if self.location.is_none() {
+            self.location = other.location;
         }
 
         if self.album_art_url.is_none() {
@@ -80,5 +216,133 @@ impl Album {
         if self.artist_art_url.is_none() {
             self.artist_art_url = other.artist_art_url;
         }
+
+        if self.url.is_empty() {
+            self.url = other.url;
+        }
+
+        if self.id.is_none() {
+            self.id = other.id;
+        }
+
+        if self.about.is_none() {
+            self.about = other.about;
+        }
+
+        if self.credits.is_none() {
+            self.credits = other.credits;
+        }
+
+        if self.label.is_none() {
+            self.label = other.label;
+        }
+
+        if self.num_tracks == 0 {
+            self.num_tracks = other.num_tracks;
+        }
+
+        if self.catalog.is_none() {
+            self.catalog = other.catalog;
+        }
+
+        if self.release_type.is_none() {
+            self.release_type = other.release_type;
+        }
+    }
+}
+
+/// Kind of item found by [`crate::spider::search`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum SearchKind {
+    Artist,
+    Label,
+    Album,
+    Track,
+}
+
+impl SearchKind {
+    /// Parse the `type` of bandcamp's search results, ie: `b`, `a` or `t`,
+    /// bands being either artists or labels.
+    #[must_use]
+    pub fn from_code(code: &str, is_label: bool) -> Option<Self> {
+        match code {
+            "b" if is_label => Some(Self::Label),
+            "b" => Some(Self::Artist),
+            "a" => Some(Self::Album),
+            "t" => Some(Self::Track),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SearchKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Artist => "artist",
+            Self::Label => "label",
+            Self::Album => "album",
+            Self::Track => "track",
+        })
+    }
+}
+
+/// An item found by [`crate::spider::search`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchResult {
+    pub kind: SearchKind,
+    pub name: String,
+    /// Artist of an album or track
+    pub band_name: Option<String>,
+    /// Album of a track
+    pub album_name: Option<String>,
+    /// Url of the item's page on bandcamp
+    pub url: String,
+    pub location: Option<String>,
+    pub genre: Option<String>,
+    pub art_url: Option<String>,
+    /// Bandcamp's ID of the item
+    pub id: Option<u64>,
+}
+
+impl SearchResult {
+    /// Url to pass to [`crate::spider::fetch_albums`], the discography page for artists & labels.
+    #[must_use]
+    pub fn download_url(&self) -> String {
+        match self.kind {
+            SearchKind::Artist | SearchKind::Label => {
+                format!("{}/music", self.url.trim_end_matches('/'))
+            }
+            SearchKind::Album | SearchKind::Track => self.url.clone(),
+        }
+    }
+}
+
+/// (De)serialize durations as seconds, eg: `201.5`.
+#[cfg(feature = "serde")]
+mod secs {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[allow(clippy::ref_option)]
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => serializer.serialize_some(&duration.as_secs_f64()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Option::<f64>::deserialize(deserializer)?
+            .map(|secs| Duration::try_from_secs_f64(secs).map_err(serde::de::Error::custom))
+            .transpose()
     }
 }
@@ -0,0 +1,172 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+
+/// Largest difference allowed between the decoded & the expected duration of a stream.
+const DURATION_TOLERANCE: Duration = Duration::from_secs(2);
+
+/// Summary of a well-formed MPEG audio stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stream {
+    /// Number of MPEG frames
+    pub frames: usize,
+    /// Duration computed from the frames' sample counts
+    pub duration: Duration,
+}
+
+struct FrameHeader {
+    len: usize,
+    samples: u32,
+    sample_rate: u32,
+}
+
+/// Parse the 4 bytes MPEG audio frame header at the start of `bytes`.
+fn frame_header(bytes: &[u8]) -> Option<FrameHeader> {
+    // kbps, indexed by [version is MPEG-1][layer - 1][bitrate index]
+    const BITRATES: [[[u32; 15]; 3]; 2] = [
+        [
+            [
+                0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+            ],
+            [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+            [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+        ],
+        [
+            [
+                0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+            ],
+            [
+                0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+            ],
+            [
+                0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+            ],
+        ],
+    ];
+
+    let &[b0, b1, b2, _] = bytes.get(..4)? else {
+        return None;
+    };
+
+    if b0 != 0xff || b1 & 0xe0 != 0xe0 {
+        return None;
+    }
+
+    // 0: MPEG-2.5, 2: MPEG-2, 3: MPEG-1
+    let version = (b1 >> 3) & 0b11;
+    // 1: layer III, 2: layer II, 3: layer I
+    let layer = match (b1 >> 1) & 0b11 {
+        0 => return None,
+        bits => 4 - bits,
+    };
+    let bitrate_index = usize::from(b2 >> 4);
+    let sample_rate_index = usize::from((b2 >> 2) & 0b11);
+    let padding = u32::from((b2 >> 1) & 1);
+
+    if version == 1 || bitrate_index == 0 || bitrate_index == 15 || sample_rate_index == 3 {
+        return None;
+    }
+
+    let mpeg1 = version == 3;
+    let bitrate = BITRATES[usize::from(mpeg1)][usize::from(layer - 1)][bitrate_index] * 1000;
+    let sample_rate = [44100, 48000, 32000][sample_rate_index]
+        >> match version {
+            3 => 0,
+            2 => 1,
+            _ => 2,
+        };
+
+    let (samples, len) = match layer {
+        1 => (384, (12 * bitrate / sample_rate + padding) * 4),
+        2 => (1152, 144 * bitrate / sample_rate + padding),
+        _ if mpeg1 => (1152, 144 * bitrate / sample_rate + padding),
+        _ => (576, 72 * bitrate / sample_rate + padding),
+    };
+
+    Some(FrameHeader {
+        len: len as usize,
+        samples,
+        sample_rate,
+    })
+}
+
+/// Size of the ID3v2 tag at the start of `data`, including its header (and footer if any).
+#[must_use]
+pub fn id3v2_size(data: &[u8]) -> usize {
+    match data.get(..10) {
+        Some(header) if header.starts_with(b"ID3") => {
+            // size is stored as a 28 bit "syncsafe" integer
+            let size = header[6..]
+                .iter()
+                .fold(0usize, |acc, byte| (acc << 7) | usize::from(byte & 0x7f));
+            let footer = if header[5] & 0x10 == 0 { 0 } else { 10 };
+
+            10 + size + footer
+        }
+        _ => 0,
+    }
+}
+
+/// Walk the MPEG frames of `data`, an mp3 file with optional ID3v2 & ID3v1 tags,
+/// and check that they follow each other up to the end of the file.
+pub fn parse(data: &[u8]) -> Result<Stream> {
+    let mut offset = id3v2_size(data);
+    let mut end = data.len();
+
+    if end >= offset + 128 && data[end - 128..].starts_with(b"TAG") {
+        end -= 128;
+    }
+
+    // some encoders pad the ID3v2 tag without accounting it in its size
+    while data.get(offset) == Some(&0) {
+        offset += 1;
+    }
+
+    let mut frames = 0;
+    let mut duration = 0.0;
+
+    // a few trailing bytes which can't even hold a frame header are tolerated
+    while offset + 4 <= end {
+        let Some(header) = frame_header(&data[offset..]) else {
+            bail!("invalid MPEG frame header at byte {offset}");
+        };
+
+        if offset + header.len > end {
+            bail!(
+                "truncated MPEG frame at byte {offset}, {} of {} bytes",
+                end - offset,
+                header.len
+            );
+        }
+
+        frames += 1;
+        duration += f64::from(header.samples) / f64::from(header.sample_rate);
+        offset += header.len;
+    }
+
+    if frames == 0 {
+        bail!("no MPEG frames found");
+    }
+
+    Ok(Stream {
+        frames,
+        duration: Duration::from_secs_f64(duration),
+    })
+}
+
+/// [`parse`] `data` and check that its duration matches `expected`, if known.
+pub fn verify(data: &[u8], expected: Option<Duration>) -> Result<Stream> {
+    let stream = parse(data)?;
+
+    if let Some(expected) = expected {
+        if stream.duration.abs_diff(expected) > DURATION_TOLERANCE {
+            bail!(
+                "duration is {:.1}s, expected {:.1}s",
+                stream.duration.as_secs_f64(),
+                expected.as_secs_f64()
+            );
+        }
+    }
+
+    Ok(stream)
+}
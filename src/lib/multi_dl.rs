@@ -1,70 +1,227 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::channel;
 use std::time::Duration;
 use std::{fs, io, thread};
 
-use anyhow::{anyhow, Context, Result};
-use console::style;
+use anyhow::{bail, Context, Result};
 use curl::easy::{Easy2, Handler, WriteError};
-use curl::multi::{Easy2Handle, Message, Multi};
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use curl::multi::{Easy2Handle, Multi};
 
 use crate::{
     client::{self, user_agent},
+    events::{Event, OnEvent, Skip},
     models::{Album, Track},
-    utils::{make_path, tag_mp3, timestamp, track_path},
+    mp3,
+    replaygain::{self, Analysis},
+    utils::{
+        prepare_directory, tag_mp3, tag_replay_gain, track_path, write_lyrics_file, OnExists,
+        TagProfile,
+    },
 };
 
-type Config<'a> = (&'a Track, PathBuf);
-type RunConfig<'a> = Vec<Config<'a>>;
+/// A track to download, along with its album & the file it is saved to.
+struct Config<'a> {
+    album: &'a Album,
+    track: &'a Track,
+    path: PathBuf,
+}
 
-struct Collector<'a>(Vec<u8>, ProgressBar, Config<'a>);
+/// A track is downloaded this many times before giving up on it
+const MAX_ATTEMPTS: u32 = 3;
+
+struct Collector<'a> {
+    buf: Vec<u8>,
+    /// Position in the download queue
+    id: usize,
+    on_event: &'a OnEvent,
+    cfg: Config<'a>,
+    /// `Content-Length` of the last response
+    content_length: Option<u64>,
+    attempt: u32,
+}
 
 impl Handler for Collector<'_> {
     fn write(&mut self, data: &[u8]) -> Result<usize, WriteError> {
-        self.0.extend_from_slice(data);
+        self.buf.extend_from_slice(data);
         Ok(data.len())
     }
 
+    fn header(&mut self, data: &[u8]) -> bool {
+        let line = String::from_utf8_lossy(data);
+
+        if line.starts_with("HTTP/") {
+            // start of a new response, eg: after a redirect
+            self.content_length = None;
+        } else if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                self.content_length = value.trim().parse().ok();
+            }
+        }
+
+        true
+    }
+
     fn progress(&mut self, dltotal: f64, dlnow: f64, _: f64, _: f64) -> bool {
-        self.1.set_length(dltotal as u64);
-        self.1.set_position(dlnow as u64);
+        if dlnow > 0.0 {
+            (self.on_event)(&Event::Progress {
+                id: self.id,
+                downloaded: dlnow as u64,
+                total: dltotal as u64,
+            });
+        }
+
         true
     }
 }
 
-pub struct Downloader<'a> {
-    tracks: RunConfig<'a>,
-    progress_meter: MultiProgress,
+impl Collector<'_> {
+    /// Check that the whole body was received and that it is a well-formed mp3 file,
+    /// as long as the track.
+    fn verify(&self) -> Result<()> {
+        if let Some(len) = self.content_length {
+            if self.buf.len() as u64 != len {
+                bail!("received {} of {len} bytes", self.buf.len());
+            }
+        }
+
+        mp3::verify(&self.buf, self.cfg.track.duration)?;
+
+        Ok(())
+    }
+}
+
+/// Analyses of each album's finished tracks, to tag them with the album's ReplayGain
+/// once all its tracks in the queue are done.
+#[derive(Default)]
+struct AlbumGain {
+    /// Number of tracks not done yet, by album directory
+    pending: HashMap<PathBuf, usize>,
+    analyses: HashMap<PathBuf, Vec<(PathBuf, Analysis)>>,
+}
+
+impl AlbumGain {
+    fn new<'p>(paths: impl Iterator<Item = &'p Path>) -> Self {
+        let mut gain = Self::default();
+
+        for dir in paths.filter_map(Path::parent) {
+            *gain.pending.entry(dir.to_path_buf()).or_default() += 1;
+        }
+
+        gain
+    }
+
+    /// Mark the track at `path` as done, `analysis` is `None` if it failed.
+    ///
+    /// Once the album's last track is done, failing to tag its gain is reported to `on_event`
+    /// rather than stopping the other downloads.
+    fn done(
+        &mut self,
+        path: &Path,
+        analysis: Option<Analysis>,
+        profile: &TagProfile,
+        on_event: &OnEvent,
+    ) {
+        let Some(dir) = path.parent() else {
+            return;
+        };
+
+        if let Some(analysis) = analysis {
+            self.analyses
+                .entry(dir.to_path_buf())
+                .or_default()
+                .push((path.to_path_buf(), analysis));
+        }
+
+        let Some(pending) = self.pending.get_mut(dir) else {
+            return;
+        };
+
+        *pending -= 1;
+
+        if *pending > 0 {
+            return;
+        }
+
+        let tracks = self.analyses.remove(dir).unwrap_or_default();
+        let analyses = tracks
+            .iter()
+            .map(|(_, analysis)| analysis.clone())
+            .collect::<Vec<_>>();
+
+        for ((path, _), gain) in tracks.iter().zip(replaygain::album(&analyses)) {
+            if let Err(error) = tag_replay_gain(&gain, path, profile)
+                .with_context(|| format!("Failed to tag the album gain of {}", path.display()))
+            {
+                on_event(&Event::AlbumGainFailed {
+                    directory: dir,
+                    error: &error,
+                });
+            }
+        }
+    }
+}
+
+pub struct Downloader {
     client: Multi,
 }
 
-impl<'a> Downloader<'a> {
-    pub fn run(dump: RunConfig<'a>, track_format: Option<&String>) -> Result<()> {
+impl Downloader {
+    /// Download tracks of `albums` to their directory under `path`, see [`prepare_directory`].
+    ///
+    /// Tracks without a stream url are skipped, as well as existing ones depending on `on_exists`,
+    /// progress is reported to `on_event`.
+    pub fn run(
+        albums: &[Album],
+        path: Option<&PathBuf>,
+        track_format: Option<&String>,
+        on_exists: OnExists,
+        profile: &TagProfile,
+        on_event: &OnEvent,
+    ) -> Result<()> {
         let dl = Self {
-            tracks: dump,
-            progress_meter: MultiProgress::new(),
             client: Multi::new(),
         };
 
         let tf = track_format.map_or_else(String::new, std::clone::Clone::clone);
 
-        let handles = dl
-            .tracks
-            .iter()
-            .enumerate()
-            .filter(|(_, (track, root))| {
-                if make_path(track, root, &tf).exists() {
-                    eprintln!("`{}` already exists, skipping", track.name);
-                    false
+        let mut queue = vec![];
+
+        for album in albums {
+            let root = prepare_directory(path, album)?;
+
+            for track in &album.tracks {
+                let reason = if track.url.is_empty() {
+                    Skip::NoStreamUrl
+                } else if let Some(path) = track_path(album, track, &root, &tf, on_exists) {
+                    queue.push(Config { album, track, path });
+                    continue;
+                } else if on_exists == OnExists::Verify {
+                    Skip::Intact
                 } else {
-                    true
-                }
-            })
-            .map(|(token, track)| Ok((token, dl.download(token, track.clone())?)))
+                    Skip::Exists
+                };
+
+                on_event(&Event::Skipped {
+                    album,
+                    track,
+                    reason,
+                });
+            }
+        }
+
+        let mut handles = queue
+            .into_iter()
+            .enumerate()
+            .map(|(token, cfg)| Ok((token, dl.download(token, cfg, on_event)?)))
             .collect::<Result<HashMap<_, _>>>()?;
 
+        let mut album_gain = AlbumGain::new(
+            handles
+                .values()
+                .map(|handle| handle.get_ref().cfg.path.as_path()),
+        );
+
         let mut still_alive = true;
 
         while still_alive {
@@ -72,39 +229,115 @@ impl<'a> Downloader<'a> {
                 still_alive = false;
             }
 
+            let mut failed = vec![];
+
             dl.client.messages(|message| {
-                let _ = message_handler(&message, &handles, &tf)
-                    .context("Failed to process downloaded item(s)");
+                let Ok(token) = message.token() else {
+                    return;
+                };
+
+                let handle = &handles[&token];
+
+                let transfer = message
+                    .result_for2(handle)
+                    .expect("token mismatch with the `EasyHandle`");
+
+                match transfer
+                    .map_err(Into::into)
+                    .and_then(|()| handle.get_ref().verify())
+                {
+                    Ok(()) => {
+                        let collector = handle.get_ref();
+                        let id = collector.id;
+
+                        on_event(&Event::Tagging { id });
+
+                        let ret = message_handler(collector, profile)
+                            .context("Failed to process downloaded item(s)");
+
+                        // analyzed before the track is reported as completed
+                        let analysis = match &ret {
+                            Ok(()) if profile.replay_gain => replay_gain(collector, profile)
+                                .inspect_err(|error| {
+                                    on_event(&Event::ReplayGainFailed { id, error });
+                                })
+                                .ok(),
+                            _ => None,
+                        };
+
+                        match &ret {
+                            Ok(()) => on_event(&Event::Completed { id }),
+                            Err(error) => on_event(&Event::Failed { id, error }),
+                        }
+
+                        if profile.replay_gain {
+                            album_gain.done(&collector.cfg.path, analysis, profile, on_event);
+                        }
+                    }
+                    Err(error) => failed.push((token, error)),
+                }
             });
 
+            for (token, error) in failed {
+                let Collector {
+                    id, cfg, attempt, ..
+                } = handles[&token].get_ref();
+
+                if *attempt < MAX_ATTEMPTS {
+                    on_event(&Event::Retrying {
+                        id: *id,
+                        attempt: attempt + 1,
+                        error: &error,
+                    });
+
+                    let handle = handles
+                        .remove(&token)
+                        .expect("handle of a finished transfer");
+
+                    handles.insert(token, dl.retry(token, handle)?);
+                    still_alive = true;
+                } else {
+                    on_event(&Event::Failed {
+                        id: *id,
+                        error: &error,
+                    });
+
+                    if profile.replay_gain {
+                        album_gain.done(&cfg.path, None, profile, on_event);
+                    }
+                }
+            }
+
             if still_alive {
                 dl.client.wait(&mut [], Duration::from_secs(1))?;
             }
         }
 
-        // dl.progress_meter.println("Downloaded")?;
-
         Ok(())
     }
 
-    fn download(&'a self, token: usize, cfg: Config<'a>) -> Result<Easy2Handle<Collector>> {
-        let pb = self.progress_meter.add(
-            ProgressBar::new(0).with_style(
-                ProgressStyle::with_template(
-                    "{prefix} {msg}\n[{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})",
-                )?
-                .progress_chars("=> "),
-            ),
-        );
-
-        let prefix = PathBuf::from(cfg.0.album.artist.clone())
-            .join(cfg.0.album.album.clone())
-            .join(cfg.0.name.clone());
-
-        pb.set_prefix(prefix.display().to_string());
-
-        let url = &cfg.0.url;
-        let mut request = Easy2::new(Collector(Vec::new(), pb, cfg));
+    fn download<'a>(
+        &self,
+        token: usize,
+        cfg: Config<'a>,
+        on_event: &'a OnEvent,
+    ) -> Result<Easy2Handle<Collector<'a>>> {
+        on_event(&Event::Queued {
+            id: token,
+            album: cfg.album,
+            track: cfg.track,
+            path: &cfg.path,
+        });
+
+        let url = &cfg.track.url;
+        let mut request = Easy2::new(Collector {
+            buf: Vec::new(),
+            id: token,
+            on_event,
+            cfg,
+            content_length: None,
+            attempt: 1,
+        });
 
         request.url(&url[..])?;
         request.useragent(&user_agent())?;
@@ -115,61 +348,59 @@ impl<'a> Downloader<'a> {
 
         Ok(handle)
     }
-}
 
-fn message_handler(
-    message: &Message,
-    handles: &HashMap<usize, Easy2Handle<Collector>>,
-    track_fmt: &String,
-) -> Result<()> {
-    let token = message.token().map_err(|err| anyhow!("{err}"))?;
-    let handle = &handles[&token];
-
-    let Collector(buf, bar, (track, root)) = handle.get_ref();
-    let Album {
-        release_date,
-        album_art_url,
-        ..
-    } = &track.album;
-
-    match message
-        .result_for2(handle)
-        .expect("token mismatch with the `EasyHandle`")
-    {
-        Ok(()) => {
-            bar.set_message("📥");
-            let path = track_path(track, root, track_fmt)?;
+    /// Download a failed track again.
+    fn retry<'a>(
+        &self,
+        token: usize,
+        handle: Easy2Handle<Collector<'a>>,
+    ) -> Result<Easy2Handle<Collector<'a>>> {
+        let mut request = self.client.remove2(handle)?;
+        let collector = request.get_mut();
 
-            let mut file = fs::File::create(&path)?;
+        collector.buf.clear();
+        collector.content_length = None;
+        collector.attempt += 1;
 
-            io::copy(&mut buf.as_slice(), &mut file)?;
+        let mut handle = self.client.add2(request)?;
+        handle.set_token(token)?;
 
-            bar.set_message("💾");
+        Ok(handle)
+    }
+}
 
-            let album_art_url = album_art_url.clone().unwrap_or_default();
+fn message_handler(collector: &Collector, profile: &TagProfile) -> Result<()> {
+    let Collector {
+        buf,
+        cfg: Config { album, track, path },
+        ..
+    } = collector;
 
-            let album_art = if album_art_url.is_empty() {
-                None
-            } else if let Ok(album_art) = offload(album_art_url) {
-                Some(album_art)
-            } else {
-                None
-            };
+    let mut file = fs::File::create(path)?;
 
-            tag_mp3(album_art, timestamp(release_date), track, &path)?;
+    io::copy(&mut buf.as_slice(), &mut file)?;
 
-            bar.println(format!("{} {}", bar.prefix(), style("✔").green()));
+    let album_art = album
+        .album_art_url
+        .as_ref()
+        .and_then(|url| profile.art_url(url))
+        .and_then(|url| offload(url).ok());
 
-            bar.finish_and_clear();
-        }
-        Err(error) => {
-            println!("E: {} - <{}>", error, track.url);
-        }
-    }
+    tag_mp3(album_art, album, track, path, profile)?;
+    write_lyrics_file(album, track, path, profile)?;
 
     Ok(())
 }
 
+/// Analyze the downloaded track and tag it with its ReplayGain.
+fn replay_gain(collector: &Collector, profile: &TagProfile) -> Result<Analysis> {
+    let analysis = replaygain::analyze(&collector.buf)?;
+
+    tag_replay_gain(&analysis.replay_gain(), &collector.cfg.path, profile)?;
+
+    Ok(analysis)
+}
+
 fn offload(url: String) -> Result<Vec<u8>> {
     let (tx, rx) = channel();
 
@@ -0,0 +1,212 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    models::{Album, Track},
+    utils::parse_date,
+};
+
+/// Fields of an album to replace, `None` ones are left as scraped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AlbumOverride {
+    pub album: Option<String>,
+    pub artist: Option<String>,
+    /// Replaces the album's keywords, see [`crate::utils::TagProfile::genres`]
+    pub genres: Option<Vec<String>>,
+    /// In any format [`parse_date`] understands
+    pub date: Option<String>,
+    /// Keyed by track number
+    pub tracks: HashMap<i32, TrackOverride>,
+}
+
+/// Fields of a track to replace, `None` ones are left as scraped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackOverride {
+    pub name: Option<String>,
+    pub artist: Option<String>,
+}
+
+/// User provided metadata, replacing what was scraped from bandcamp.
+///
+/// Parsed from a JSON object keyed by album url or bandcamp ID, eg:
+///
+/// ```json
+/// {
+///   "https://artist.bandcamp.com/album/album": {
+///     "album": "Album",
+///     "artist": "Artist",
+///     "genres": ["ambient", "drone"],
+///     "date": "2020-01-31",
+///     "tracks": { "2": { "name": "Track", "artist": "Someone" } }
+///   },
+///   "1234567890": { "album": "Another Album" }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Overrides(HashMap<String, AlbumOverride>);
+
+fn string(value: &gjson::Value, key: &str) -> Result<Option<String>> {
+    let value = value.get(key);
+
+    match value.kind() {
+        gjson::Kind::Null => Ok(None),
+        gjson::Kind::String => Ok(Some(value.to_string())),
+        _ => bail!("`{key}` must be a string"),
+    }
+}
+
+impl Overrides {
+    pub fn parse(json: &str) -> Result<Self> {
+        if !gjson::valid(json) {
+            bail!("invalid JSON");
+        }
+
+        let root = gjson::parse(json);
+
+        if root.kind() != gjson::Kind::Object {
+            bail!("expected an object keyed by album url or ID");
+        }
+
+        let mut overrides = HashMap::new();
+        let mut ret = Ok(());
+
+        root.each(|key, value| {
+            ret = Self::parse_album(&value)
+                .with_context(|| format!("in `{key}`"))
+                .map(|album| {
+                    overrides.insert(normalize(key.str()), album);
+                });
+
+            ret.is_ok()
+        });
+
+        ret.map(|()| Self(overrides))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path)?;
+
+        Self::parse(&json).with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    fn parse_album(value: &gjson::Value) -> Result<AlbumOverride> {
+        if value.kind() != gjson::Kind::Object {
+            bail!("expected an object");
+        }
+
+        let genres = match value.get("genres") {
+            genres if genres.kind() == gjson::Kind::Null => None,
+            genres if genres.kind() == gjson::Kind::Array => Some(
+                genres
+                    .array()
+                    .iter()
+                    .map(|genre| genre.str().trim().to_string())
+                    .filter(|genre| !genre.is_empty())
+                    .collect(),
+            ),
+            _ => bail!("`genres` must be an array of strings"),
+        };
+
+        let date = string(value, "date")?;
+
+        if let Some(date) = &date {
+            if parse_date(date).is_none() {
+                bail!("unknown date format `{date}`, expected eg: 2020-01-31");
+            }
+        }
+
+        let mut tracks = HashMap::new();
+        let mut ret = Ok(());
+
+        value.get("tracks").each(|num, track| {
+            ret = Self::parse_track(&num, &track).map(|(num, track)| {
+                tracks.insert(num, track);
+            });
+
+            ret.is_ok()
+        });
+
+        ret?;
+
+        Ok(AlbumOverride {
+            album: string(value, "album")?,
+            artist: string(value, "artist")?,
+            genres,
+            date,
+            tracks,
+        })
+    }
+
+    fn parse_track(num: &gjson::Value, track: &gjson::Value) -> Result<(i32, TrackOverride)> {
+        let Ok(num) = num.str().parse() else {
+            bail!("tracks are keyed by number, found `{}`", num.str());
+        };
+
+        Ok((
+            num,
+            TrackOverride {
+                name: string(track, "name")?,
+                artist: string(track, "artist")?,
+            },
+        ))
+    }
+
+    /// Overrides of `album`, looked up by its url, then its ID.
+    #[must_use]
+    pub fn get(&self, album: &Album) -> Option<&AlbumOverride> {
+        self.0
+            .get(&normalize(&album.url))
+            .or_else(|| self.0.get(&album.id?.to_string()))
+    }
+
+    /// Replace fields of `album` & its tracks, if it has overrides.
+    pub fn apply(&self, album: &mut Album) {
+        let Some(fields) = self.get(album) else {
+            return;
+        };
+
+        apply_album(fields, album);
+
+        for track in &mut album.tracks {
+            apply_track(fields, track);
+        }
+    }
+}
+
+fn apply_album(fields: &AlbumOverride, album: &mut Album) {
+    if let Some(name) = &fields.album {
+        album.album.clone_from(name);
+    }
+
+    if let Some(artist) = &fields.artist {
+        album.artist.clone_from(artist);
+    }
+
+    if let Some(genres) = &fields.genres {
+        album.tags.clone_from(genres);
+    }
+
+    if let Some(date) = &fields.date {
+        album.release_date.clone_from(date);
+        album.released = parse_date(date);
+    }
+}
+
+fn apply_track(fields: &AlbumOverride, track: &mut Track) {
+    let Some(fields) = fields.tracks.get(&track.num) else {
+        return;
+    };
+
+    if let Some(name) = &fields.name {
+        track.name.clone_from(name);
+    }
+
+    if let Some(artist) = &fields.artist {
+        track.artist = Some(artist.clone());
+    }
+}
+
+fn normalize(key: &str) -> String {
+    key.trim().trim_end_matches('/').to_string()
+}
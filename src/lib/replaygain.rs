@@ -0,0 +1,288 @@
+use std::{f64::consts::PI, io::Cursor};
+
+use anyhow::{anyhow, Result};
+use symphonia::core::{
+    audio::SampleBuffer, codecs::DecoderOptions, errors::Error, formats::FormatOptions,
+    io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+};
+
+/// Loudness ReplayGain 2.0 brings tracks to, in LUFS
+const REFERENCE_LOUDNESS: f64 = -18.0;
+
+/// Blocks quieter than this are ignored, in LUFS
+const ABSOLUTE_GATE: f64 = -70.0;
+
+/// Blocks quieter than the ungated loudness minus this are ignored, in LU
+const RELATIVE_GATE: f64 = 10.0;
+
+/// ReplayGain values to tag a track with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayGain {
+    /// In dB
+    pub track_gain: f64,
+    pub track_peak: f64,
+    /// In dB, only known once all tracks of the album are analyzed
+    pub album_gain: Option<f64>,
+    pub album_peak: Option<f64>,
+}
+
+/// Loudness measurement of a track, as per EBU R128 / ITU-R BS.1770.
+#[derive(Debug, Clone, Default)]
+pub struct Analysis {
+    /// Mean square of each 400ms block (overlapping by 75%), summed over channels
+    blocks: Vec<f64>,
+    /// Largest absolute sample value
+    peak: f64,
+}
+
+impl Analysis {
+    /// Integrated loudness in LUFS, `None` if the track is silent.
+    #[must_use]
+    pub fn loudness(&self) -> Option<f64> {
+        integrated_loudness(&self.blocks)
+    }
+
+    /// ReplayGain of the track alone.
+    #[must_use]
+    pub fn replay_gain(&self) -> ReplayGain {
+        ReplayGain {
+            track_gain: gain(self.loudness()),
+            track_peak: self.peak,
+            album_gain: None,
+            album_peak: None,
+        }
+    }
+}
+
+/// ReplayGain of each track of an album, in the order of `tracks`.
+///
+/// Album gain is computed from the blocks of all tracks together, as if they were one track.
+#[must_use]
+pub fn album(tracks: &[Analysis]) -> Vec<ReplayGain> {
+    let blocks = tracks
+        .iter()
+        .flat_map(|track| track.blocks.iter().copied())
+        .collect::<Vec<_>>();
+
+    let album_gain = gain(integrated_loudness(&blocks));
+    let album_peak = tracks.iter().map(|track| track.peak).fold(0.0, f64::max);
+
+    tracks
+        .iter()
+        .map(|track| ReplayGain {
+            album_gain: Some(album_gain),
+            album_peak: Some(album_peak),
+            ..track.replay_gain()
+        })
+        .collect()
+}
+
+fn gain(loudness: Option<f64>) -> f64 {
+    loudness.map_or(0.0, |loudness| REFERENCE_LOUDNESS - loudness)
+}
+
+fn block_loudness(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+fn integrated_loudness(blocks: &[f64]) -> Option<f64> {
+    let mean = |blocks: &mut dyn Iterator<Item = f64>| {
+        let (sum, count) = blocks.fold((0.0, 0), |(sum, count), energy| (sum + energy, count + 1));
+
+        (count > 0).then(|| sum / f64::from(count))
+    };
+
+    let gated = || {
+        blocks
+            .iter()
+            .copied()
+            .filter(|energy| *energy > 0.0 && block_loudness(*energy) > ABSOLUTE_GATE)
+    };
+
+    let relative_gate = block_loudness(mean(&mut gated())?) - RELATIVE_GATE;
+
+    mean(&mut gated().filter(|energy| block_loudness(*energy) > relative_gate)).map(block_loudness)
+}
+
+/// Second order IIR filter.
+#[derive(Clone, Copy, Default)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+
+        y
+    }
+}
+
+/// K-weighting filter of BS.1770: a high shelf followed by a high pass,
+/// with coefficients derived for any sample rate.
+fn k_weighting(rate: f64) -> [Biquad; 2] {
+    let (f0, gain, q) = (
+        1_681.974_450_955_533,
+        3.999_843_853_973_347,
+        0.707_175_236_955_419_6,
+    );
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain / 20.0);
+    let vb = vh.powf(0.499_666_774_154_541_6);
+    let a0 = 1.0 + k / q + k * k;
+
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        ..Default::default()
+    };
+
+    let (f0, q) = (38.135_470_876_024_44, 0.500_327_037_323_877_3);
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        ..Default::default()
+    };
+
+    [shelf, high_pass]
+}
+
+/// Accumulates K-weighted energy of interleaved samples into 400ms blocks.
+struct Meter {
+    filters: Vec<[Biquad; 2]>,
+    /// Sum of squares of each channel for the current 100ms step
+    step_energy: f64,
+    step_len: usize,
+    step_pos: usize,
+    /// Energy of the last 4 steps
+    steps: Vec<f64>,
+    analysis: Analysis,
+}
+
+impl Meter {
+    fn new(channels: usize, rate: u32) -> Self {
+        Self {
+            filters: vec![k_weighting(f64::from(rate)); channels],
+            step_energy: 0.0,
+            step_len: (rate / 10) as usize,
+            step_pos: 0,
+            steps: Vec::with_capacity(4),
+            analysis: Analysis::default(),
+        }
+    }
+
+    fn feed(&mut self, samples: &[f32]) {
+        let channels = self.filters.len();
+
+        for frame in samples.chunks_exact(channels) {
+            for (sample, [shelf, high_pass]) in frame.iter().zip(&mut self.filters) {
+                let sample = f64::from(*sample);
+                let weighted = high_pass.process(shelf.process(sample));
+
+                self.analysis.peak = self.analysis.peak.max(sample.abs());
+                self.step_energy += weighted * weighted;
+            }
+
+            self.step_pos += 1;
+
+            if self.step_pos == self.step_len {
+                if self.steps.len() == 4 {
+                    self.steps.remove(0);
+                }
+
+                self.steps.push(self.step_energy);
+
+                if self.steps.len() == 4 {
+                    let block = self.steps.iter().sum::<f64>() / (4 * self.step_len) as f64;
+                    self.analysis.blocks.push(block);
+                }
+
+                self.step_energy = 0.0;
+                self.step_pos = 0;
+            }
+        }
+    }
+}
+
+/// Measure loudness of already decoded, interleaved samples.
+#[must_use]
+pub fn analyze_samples(samples: &[f32], channels: usize, rate: u32) -> Analysis {
+    let mut meter = Meter::new(channels, rate);
+
+    meter.feed(samples);
+    meter.analysis
+}
+
+/// Decode the mp3 file `data` and measure its loudness.
+pub fn analyze(data: &[u8]) -> Result<Analysis> {
+    let source = MediaSourceStream::new(Box::new(Cursor::new(data.to_vec())), Default::default());
+
+    let mut hint = Hint::new();
+    hint.with_extension("mp3");
+
+    let mut format = symphonia::default::get_probe()
+        .format(
+            &hint,
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?
+        .format;
+
+    let track = format
+        .default_track()
+        .ok_or_else(|| anyhow!("no audio track found"))?;
+    let track_id = track.id;
+
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    let mut meter = None;
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err.into()),
+        };
+
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // a corrupted frame is skipped, like players do
+            Err(Error::DecodeError(_)) => continue,
+            Err(err) => return Err(err.into()),
+        };
+
+        let spec = *decoded.spec();
+        let mut samples = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+
+        samples.copy_interleaved_ref(decoded);
+
+        meter
+            .get_or_insert_with(|| Meter::new(spec.channels.count(), spec.rate))
+            .feed(samples.samples());
+    }
+
+    meter
+        .map(|meter| meter.analysis)
+        .ok_or_else(|| anyhow!("no audio decoded"))
+}
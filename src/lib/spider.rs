@@ -1,16 +1,22 @@
-use std::time::Duration;
+use std::{
+    num::NonZeroUsize,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::Duration,
+};
 
 use anyhow::{anyhow, bail, Result};
-use console::style;
+#[cfg(feature = "search")]
 use curl::easy::List;
 use html_escape::decode_html_entities;
-use indicatif::{ProgressBar, ProgressStyle};
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use scraper::{Html, Selector};
 
+#[cfg(feature = "search")]
+use crate::models::{SearchKind, SearchResult};
 use crate::{
     client,
-    models::{Album, Track},
+    models::{Album, ReleaseType, Track},
+    utils::{parse_date, parse_duration},
 };
 
 fn find_track_by_name(dom: &Html, track_name: &gjson::Value) -> Option<Track> {
@@ -25,10 +31,34 @@ fn find_track_by_name(dom: &Html, track_name: &gjson::Value) -> Option<Track> {
     })
 }
 
-/// Parse data from the node: `document.querySelector('script[type="application/ld+json"]')`
+/// Value as a `String`, `None` if it is missing or empty.
+fn non_empty(value: &gjson::Value) -> Option<String> {
+    let value = decode_html_entities(value.str().trim()).to_string();
+
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Value in seconds as a [`Duration`], `None` if it is missing, zero or out of range.
+fn seconds(value: &gjson::Value) -> Option<Duration> {
+    Some(value.f64())
+        .filter(|secs| *secs > 0.0)
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+}
+
+/// Value as an ID, `None` if it is missing or not a positive number.
+fn id(value: &gjson::Value) -> Option<u64> {
+    Some(value.u64()).filter(|id| *id > 0)
+}
+
+/// Parse data from the node: `document.querySelector('script[type="application/ld+json"]')`,
+/// `None` if the page has none, eg: an error page.
 fn scrape_by_application_ld_json(dom: &Html) -> Option<Album> {
     let selector = Selector::parse("script[type='application/ld+json']").unwrap();
-    let element = dom.select(&selector).next().unwrap();
+    let element = dom.select(&selector).next()?;
 
     let json = element.inner_html();
     let json = json.as_str();
@@ -43,31 +73,60 @@ fn scrape_by_application_ld_json(dom: &Html) -> Option<Album> {
 
     album.album = item.get("name").to_string();
 
-    let tags = item
+    album.location = non_empty(&item.get("byArtist.foundingLocation.name"))
+        .or_else(|| non_empty(&item.get("publisher.foundingLocation.name")));
+
+    // keywords mix genres & moods with the artist's city, eg: `Portland` for `Portland, Oregon`
+    let places = album.location.as_deref().map_or_else(Vec::new, |location| {
+        location
+            .split(',')
+            .chain([location])
+            .map(|place| place.trim().to_lowercase())
+            .collect()
+    });
+
+    album.tags = item
         .get("keywords")
         .array()
         .iter()
-        .filter_map(|tag| {
-            let tag = tag.str().trim();
-
-            if tag.is_empty() {
-                None
-            } else {
-                Some(tag)
-            }
-        })
-        .collect::<Vec<_>>()
-        .join(", ");
-
-    album.tags = Some(tags);
+        .map(|tag| tag.str().trim())
+        .filter(|tag| !tag.is_empty() && !places.contains(&tag.to_lowercase()))
+        .map(str::to_string)
+        .collect();
     album.release_date = item.get("datePublished").to_string();
+    album.released = parse_date(&album.release_date);
     album.album_art_url = Some(item.get("image").to_string());
     album.artist = item.get("byArtist.name").to_string();
     album.artist_art_url = Some(item.get("byArtist.image").to_string());
+    album.url = item.get(r"\@id").to_string();
+    album.about = non_empty(&item.get("description"));
+    album.credits = non_empty(&item.get("creditText"));
+    album.label = item
+        .get("albumRelease.#.recordLabel.name")
+        .array()
+        .iter()
+        .find_map(non_empty)
+        .or_else(|| non_empty(&item.get("publisher.name")));
+    album.catalog = item
+        .get("albumRelease.#.catalogNumber")
+        .array()
+        .iter()
+        .find_map(non_empty);
+    album.release_type = ReleaseType::from_schema(item.get("albumReleaseType").str())
+        // a track page is a release of its own
+        .or_else(|| (item.get(r"\@type").str() == "MusicRecording").then_some(ReleaseType::Single));
 
     let tracks = item.get("track.itemListElement");
 
     const FILE_PATH: &str = "additionalProperty.#(name=file_mp3-128).value";
+    const TRACK_ID_PATH: &str = "additionalProperty.#(name=track_id).value";
+    const ALBUM_ID_PATH: &str = "albumRelease.#.additionalProperty.#(name=item_id).value";
+
+    album.id = item.get(ALBUM_ID_PATH).array().iter().find_map(id);
+    album.num_tracks = match item.get("numTracks").u32() {
+        0 => tracks.array().len().max(1) as u32,
+        total => total,
+    };
 
     // case when current url is just a track
     album.tracks = if tracks.array().is_empty() {
@@ -88,8 +147,12 @@ fn scrape_by_application_ld_json(dom: &Html) -> Option<Album> {
             num: 1,
             name: track_name.to_string().replace("/", ":"),
             url,
-            lyrics: None,
-            album: album.clone(),
+            lyrics: non_empty(&item.get("recordingOf.lyrics.text")),
+            duration: parse_duration(item.get("duration").str()),
+            artist: None,
+            page_url: non_empty(&item.get(r"\@id")),
+            id: id(&item.get(TRACK_ID_PATH)),
+            album_id: album.id,
         }]
     } else {
         // case when current url is an album
@@ -97,7 +160,7 @@ fn scrape_by_application_ld_json(dom: &Html) -> Option<Album> {
             .array()
             .iter()
             .filter_map(|track| {
-                let mut url = track.get(&("item".to_owned() + FILE_PATH)).to_string();
+                let mut url = track.get(&format!("item.{FILE_PATH}")).to_string();
 
                 if url.is_empty() {
                     let track_name = track.get("item.name");
@@ -114,11 +177,15 @@ fn scrape_by_application_ld_json(dom: &Html) -> Option<Album> {
                 Some(Track {
                     num: track.get("position").i32(),
                     name: decode_html_entities(&track.get("item.name").to_string())
-                        .replace("/", ":")
-                        .into(),
+                        .replace("/", ":"),
                     url: decode_html_entities(&url).to_string(),
-                    lyrics: Some(track.get("item.recordingOf.lyrics.text").to_string()),
-                    album: album.clone(),
+                    lyrics: non_empty(&track.get("item.recordingOf.lyrics.text")),
+                    duration: parse_duration(track.get("item.duration").str()),
+                    artist: non_empty(&track.get("item.byArtist.name"))
+                        .filter(|artist| *artist != album.artist),
+                    page_url: non_empty(&track.get(r"item.\@id")),
+                    id: id(&track.get(&format!("item.{TRACK_ID_PATH}"))),
+                    album_id: album.id,
                 })
             })
             .collect()
@@ -130,10 +197,12 @@ fn scrape_by_application_ld_json(dom: &Html) -> Option<Album> {
 /// Parse data from the node: `document.querySelector('script[data-tralbum]')`
 fn scrape_by_data_tralbum(dom: &Html) -> Album {
     let selector = Selector::parse("script[data-tralbum]").unwrap();
-    let element = dom.select(&selector).next().unwrap();
-
     let mut album = Album::default();
 
+    let Some(element) = dom.select(&selector).next() else {
+        return album;
+    };
+
     for (name, val) in &element.value().attrs {
         let data = gjson::get(val.trim(), "@this");
 
@@ -148,6 +217,16 @@ fn scrape_by_data_tralbum(dom: &Html) -> Album {
             }
 
             album.release_date = data.get("album_release_date").to_string();
+            album.released = parse_date(&album.release_date);
+            album.url = data.get("url").to_string();
+            album.id = id(&data.get("id"));
+            album.about = non_empty(&data.get("current.about"));
+            album.credits = non_empty(&data.get("current.credits"));
+
+            album.num_tracks = data.get("trackinfo").array().len() as u32;
+
+            let base_url = url::Url::parse(&album.url).ok();
+
             album.tracks = data
                 .get("trackinfo")
                 .array()
@@ -157,8 +236,16 @@ fn scrape_by_data_tralbum(dom: &Html) -> Album {
                     num: (index + 1) as i32,
                     name: item.get("title").to_string(),
                     url: item.get("file.mp3-128").to_string(),
-                    lyrics: None,
-                    album: album.clone(),
+                    lyrics: non_empty(&item.get("lyrics")),
+                    duration: seconds(&item.get("duration")),
+                    artist: non_empty(&item.get("artist")).filter(|artist| *artist != album.artist),
+                    page_url: base_url
+                        .as_ref()
+                        .zip(non_empty(&item.get("title_link")))
+                        .and_then(|(base, link)| base.join(&link).ok())
+                        .map(String::from),
+                    id: id(&item.get("track_id")).or_else(|| id(&item.get("id"))),
+                    album_id: album.id,
                 })
                 .collect();
         }
@@ -190,7 +277,71 @@ fn get_all_album_links(dom: &Html) -> Vec<String> {
 /// Facade for `scrape_by_*` methods.
 /// Calls `scrape_by_application_ld_json` or `scrape_by_data_tralbum` internal methods if first fails.
 fn get_album(dom: &Html) -> Option<Album> {
-    scrape_by_application_ld_json(dom)
+    let mut album = scrape_by_application_ld_json(dom)?;
+
+    fill_from_tralbum(dom, &mut album);
+
+    Some(album)
+}
+
+/// Fill in artists, lyrics & durations missing from `application/ld+json` with the ones from `data-tralbum`.
+///
+/// Album pages often only tell that a track has lyrics, in that case they are scraped
+/// from the track's own page.
+fn fill_from_tralbum(dom: &Html, album: &mut Album) {
+    let selector = Selector::parse("script[data-tralbum]").unwrap();
+
+    let Some(data) = dom
+        .select(&selector)
+        .next()
+        .and_then(|element| element.value().attr("data-tralbum"))
+    else {
+        return;
+    };
+
+    let data = gjson::get(data.trim(), "trackinfo");
+    let trackinfo = data.array();
+
+    let album_artist = album.artist.clone();
+
+    for track in album.tracks.iter_mut().filter(|track| {
+        track.lyrics.is_none() || track.duration.is_none() || track.artist.is_none()
+    }) {
+        let info = trackinfo.iter().find(|info| match track.id {
+            Some(track_id) => {
+                id(&info.get("track_id")).or_else(|| id(&info.get("id"))) == Some(track_id)
+            }
+            None => info.get("title").str().replace('/', ":") == track.name,
+        });
+
+        let Some(info) = info else {
+            continue;
+        };
+
+        if track.duration.is_none() {
+            track.duration = seconds(&info.get("duration"));
+        }
+
+        if track.artist.is_none() {
+            track.artist = non_empty(&info.get("artist")).filter(|artist| *artist != album_artist);
+        }
+
+        if track.lyrics.is_some() {
+            continue;
+        }
+
+        track.lyrics = non_empty(&info.get("lyrics"));
+
+        if track.lyrics.is_none() && info.get("has_lyrics").bool() {
+            track.lyrics = track
+                .page_url
+                .as_ref()
+                .and_then(|url| fetch_html(url).ok())
+                .and_then(|page| scrape_by_application_ld_json(&page))
+                .and_then(|page| page.tracks.into_iter().next())
+                .and_then(|page_track| page_track.lyrics);
+        }
+    }
 }
 
 /// Get [`Html`] of a page.
@@ -201,27 +352,51 @@ fn fetch_html(url: &str) -> Result<Html> {
     Ok(Html::parse_document(body.as_ref()))
 }
 
+/// Fetch & scrape album pages at `urls` on a few threads, keeping their order.
+/// Pages that fail to load or parse are left out.
+fn fetch_all(urls: &[String]) -> Vec<Album> {
+    let workers = thread::available_parallelism()
+        .map_or(4, NonZeroUsize::get)
+        .min(urls.len());
+    let next = AtomicUsize::new(0);
+
+    let mut albums = thread::scope(|scope| {
+        let handles = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut albums = vec![];
+
+                    while let Some(url) = urls.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        if let Some(album) = fetch_html(url).map_or(None, |dom| get_album(&dom)) {
+                            albums.push((url, album));
+                        }
+                    }
+
+                    albums
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("album fetching thread panicked"))
+            .collect::<Vec<_>>()
+    });
+
+    albums.sort_by_key(|(url, _)| urls.iter().position(|known| known == *url));
+
+    albums.into_iter().map(|(_, album)| album).collect()
+}
+
 /// Fetch albums
 pub fn fetch_albums(url: &str) -> Result<Vec<Album>> {
-    let pb = ProgressBar::new_spinner();
-    pb.set_style(
-        ProgressStyle::default_spinner().template("{spinner} {prefix} {msg} ({elapsed})")?,
-    );
-    pb.enable_steady_tick(Duration::from_millis(100));
-    pb.set_prefix("Fetching artist's info");
-
-    let html = fetch_html(url).map_err(|err| {
-        pb.finish_with_message(style("✘").bold().red().to_string());
-        anyhow!("{err}")
-    })?;
+    let html = fetch_html(url).map_err(|err| anyhow!("{err}"))?;
 
     let is_album = html.select(&Selector::parse("#trackInfo").unwrap()).count() > 0;
 
     if is_album {
         let album = get_album(&html);
 
-        pb.finish_with_message(style("✔").bold().green().to_string());
-
         return Ok(album.into_iter().collect());
     }
 
@@ -231,12 +406,7 @@ pub fn fetch_albums(url: &str) -> Result<Vec<Album>> {
         .count() > 0;
 
     if is_discography {
-        let albums = get_all_album_links(&html)
-            .par_iter()
-            .filter_map(|url| fetch_html(url).map_or(None, |dom| get_album(&dom)))
-            .collect::<Vec<_>>();
-
-        pb.finish_with_message(style("✔").bold().green().to_string());
+        let albums = fetch_all(&get_all_album_links(&html));
 
         return Ok(albums);
     }
@@ -245,10 +415,20 @@ pub fn fetch_albums(url: &str) -> Result<Vec<Album>> {
     bail!("Invalid page.")
 }
 
-pub fn search(query: &str, query_type: &str) -> Result<()> {
-    let data = format!(
-        r#"{{"search_text":"{query}","search_filter":"{query_type}","full_page":true,"fan_id":null}}"#
-    );
+/// Search bandcamp for `query`, `query_type` being one of bandcamp's search filters:
+/// `b` for artists & labels, `a` for albums, `t` for tracks or an empty string for all.
+/// Artists & labels can't be searched apart, filter them by [`SearchResult::kind`].
+///
+/// Results are in the order of relevance given by bandcamp.
+#[cfg(feature = "search")]
+pub fn search(query: &str, query_type: &str) -> Result<Vec<SearchResult>> {
+    let data = serde_json::json!({
+        "search_text": query,
+        "search_filter": query_type,
+        "full_page": true,
+        "fan_id": null,
+    })
+    .to_string();
 
     let mut handle =
         client::handle("https://bandcamp.com/api/bcsearch_public_api/1/autocomplete_elastic")?;
@@ -261,51 +441,30 @@ pub fn search(query: &str, query_type: &str) -> Result<()> {
     handle.post_fields_copy(data.as_bytes())?;
 
     let response = client::send(handle)?;
-    let json = unsafe { gjson::get_bytes(response.as_slice(), "auto.results") };
-    let results = json.array();
-
-    if results.is_empty() {
-        eprintln!("No matching results\nTry a different `--type` or a new search keyword.");
-        return Ok(());
-    }
-
-    use term_table::{
-        row::Row,
-        table_cell::{Alignment, TableCell},
-        Table, TableStyle,
-    };
+    let json = String::from_utf8_lossy(&response);
 
-    let mut table = Table::new();
-
-    table.style = TableStyle::blank();
-
-    table.separate_rows = false;
-
-    table.add_row(Row::new(vec![
-        TableCell::new_with_alignment(style("Name").bold().underlined(), 1, Alignment::Left),
-        TableCell::new_with_alignment(style("Location").bold().underlined(), 1, Alignment::Left),
-        TableCell::new_with_alignment(style("Genre").bold().underlined(), 1, Alignment::Left),
-        TableCell::new_with_alignment(style("Url").bold().underlined(), 1, Alignment::Left),
-    ]));
-
-    let mut items = json.array();
-
-    items.sort_by_key(|item| item.get("name").str().to_lowercase());
-
-    for item in items {
-        table.add_row(Row::new(vec![
-            TableCell::new_with_alignment(item.get("name").to_string(), 1, Alignment::Left),
-            TableCell::new_with_alignment(item.get("location").to_string(), 1, Alignment::Left),
-            TableCell::new_with_alignment(item.get("genre_name").to_string(), 1, Alignment::Left),
-            TableCell::new_with_alignment(
-                item.get("item_url_root").to_string(),
-                1,
-                Alignment::Left,
-            ),
-        ]));
+    if !gjson::valid(&json) {
+        bail!("invalid response from bandcamp's search");
     }
 
-    print!("{}", table.render().trim());
+    let results = gjson::get(&json, "auto.results")
+        .array()
+        .iter()
+        .filter_map(|item| {
+            Some(SearchResult {
+                kind: SearchKind::from_code(item.get("type").str(), item.get("is_label").bool())?,
+                name: non_empty(&item.get("name"))?,
+                band_name: non_empty(&item.get("band_name")),
+                album_name: non_empty(&item.get("album_name")),
+                url: non_empty(&item.get("item_url_path"))
+                    .or_else(|| non_empty(&item.get("item_url_root")))?,
+                location: non_empty(&item.get("location")),
+                genre: non_empty(&item.get("genre_name")),
+                art_url: non_empty(&item.get("img")),
+                id: id(&item.get("id")),
+            })
+        })
+        .collect();
 
-    Ok(())
+    Ok(results)
 }
@@ -0,0 +1,191 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+#[cfg(feature = "search")]
+use crate::models::SearchResult;
+#[cfg(feature = "tagging")]
+use crate::{
+    client::MultiDownloader,
+    library,
+    models::Track,
+    utils::{album_directory, OnExists, TagProfile},
+};
+use crate::{
+    events::{Event, OnEvent},
+    models::Album,
+    overrides::Overrides,
+    spider,
+};
+
+/// Fetches, searches & downloads albums, reporting progress to a callback rather than the
+/// terminal. Built with [`Sunny::builder`].
+///
+/// ```no_run
+/// # #[cfg(feature = "tagging")] {
+/// let sunny = sunny::Sunny::builder()
+///     .path("Music")
+///     .on_event(|event| eprintln!("{event:?}"))
+///     .build();
+///
+/// let albums = sunny.fetch("https://artist.bandcamp.com/music")?;
+/// sunny.download(&albums)?;
+/// # }
+/// # anyhow::Ok(())
+/// ```
+pub struct Sunny {
+    path: Option<PathBuf>,
+    track_format: Option<String>,
+    #[cfg(feature = "tagging")]
+    on_exists: OnExists,
+    #[cfg(feature = "tagging")]
+    profile: TagProfile,
+    overrides: Option<Overrides>,
+    on_event: Box<OnEvent>,
+}
+
+/// Options of a [`Sunny`] client, all optional.
+#[derive(Default)]
+pub struct SunnyBuilder {
+    path: Option<PathBuf>,
+    track_format: Option<String>,
+    #[cfg(feature = "tagging")]
+    on_exists: OnExists,
+    #[cfg(feature = "tagging")]
+    profile: TagProfile,
+    overrides: Option<Overrides>,
+    on_event: Option<Box<OnEvent>>,
+}
+
+impl SunnyBuilder {
+    /// Root directory of downloads, the current directory by default.
+    #[must_use]
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Template of track paths, see [`crate::utils::make_path`].
+    #[must_use]
+    pub fn track_format(mut self, track_format: impl Into<String>) -> Self {
+        self.track_format = Some(track_format.into());
+        self
+    }
+
+    #[cfg(feature = "tagging")]
+    #[must_use]
+    pub fn on_exists(mut self, on_exists: OnExists) -> Self {
+        self.on_exists = on_exists;
+        self
+    }
+
+    #[cfg(feature = "tagging")]
+    #[must_use]
+    pub fn profile(mut self, profile: TagProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Applied to every fetched album.
+    #[must_use]
+    pub fn overrides(mut self, overrides: Overrides) -> Self {
+        self.overrides = Some(overrides);
+        self
+    }
+
+    /// Receive [`Event`]s while fetching & downloading, they are dropped otherwise.
+    #[must_use]
+    pub fn on_event(mut self, on_event: impl Fn(&Event) + Send + Sync + 'static) -> Self {
+        self.on_event = Some(Box::new(on_event));
+        self
+    }
+
+    #[must_use]
+    pub fn build(self) -> Sunny {
+        Sunny {
+            path: self.path,
+            track_format: self.track_format,
+            #[cfg(feature = "tagging")]
+            on_exists: self.on_exists,
+            #[cfg(feature = "tagging")]
+            profile: self.profile,
+            overrides: self.overrides,
+            on_event: self.on_event.unwrap_or_else(|| Box::new(|_| {})),
+        }
+    }
+}
+
+impl Sunny {
+    #[must_use]
+    pub fn builder() -> SunnyBuilder {
+        SunnyBuilder::default()
+    }
+
+    /// Albums of an artist, label or album page, with overrides applied.
+    pub fn fetch(&self, url: &str) -> Result<Vec<Album>> {
+        (self.on_event)(&Event::FetchStarted { url });
+
+        let mut albums = match spider::fetch_albums(url) {
+            Ok(albums) => albums,
+            Err(error) => {
+                (self.on_event)(&Event::FetchFailed { error: &error });
+                return Err(error);
+            }
+        };
+
+        if let Some(overrides) = &self.overrides {
+            albums.iter_mut().for_each(|album| overrides.apply(album));
+        }
+
+        (self.on_event)(&Event::FetchFinished {
+            albums: albums.len(),
+        });
+
+        Ok(albums)
+    }
+
+    /// See [`spider::search`].
+    #[cfg(feature = "search")]
+    pub fn search(&self, query: &str, query_type: &str) -> Result<Vec<SearchResult>> {
+        spider::search(query, query_type)
+    }
+
+    /// Download tracks of `albums`, see [`MultiDownloader::run`].
+    ///
+    /// Needs the `tagging` feature, tracks are tagged as they are written.
+    #[cfg(feature = "tagging")]
+    pub fn download(&self, albums: &[Album]) -> Result<()> {
+        MultiDownloader::run(
+            albums,
+            self.path.as_ref(),
+            self.track_format.as_ref(),
+            self.on_exists,
+            &self.profile,
+            &*self.on_event,
+        )
+    }
+
+    /// Rewrite tags of `album`'s downloaded tracks, see [`library::retag`].
+    #[cfg(feature = "tagging")]
+    pub fn retag<'a>(&self, album: &'a Album) -> Vec<(&'a Track, Result<Option<PathBuf>>)> {
+        let root = album_directory(self.path.as_ref(), album);
+
+        library::retag(
+            album,
+            &root,
+            self.track_format.as_deref().unwrap_or_default(),
+            &self.profile,
+        )
+    }
+
+    /// Directory downloads go to, see [`crate::utils::prepare_directory`].
+    #[must_use]
+    pub fn path(&self) -> Option<&PathBuf> {
+        self.path.as_ref()
+    }
+
+    #[must_use]
+    pub fn track_format(&self) -> &str {
+        self.track_format.as_deref().unwrap_or_default()
+    }
+}
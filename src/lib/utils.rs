@@ -1,24 +1,42 @@
-use anyhow::{bail, Result};
+use anyhow::Result;
+#[cfg(feature = "tagging")]
+use chrono::{Datelike, NaiveTime, Timelike};
+use chrono::{NaiveDate, NaiveDateTime};
+#[cfg(feature = "tagging")]
 use id3::{
-    frame::{Lyrics, Picture, PictureType},
-    Tag, TagLike, Timestamp, Version,
+    frame::{Comment, ExtendedText, Lyrics, Picture, PictureType},
+    Frame, Tag, TagLike, Timestamp, Version,
 };
 use strfmt::strfmt;
 
+#[cfg(feature = "tagging")]
+use std::io::Write;
 use std::{
     collections::HashMap,
     fs,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
-use super::models::{Album, Track};
+#[cfg(feature = "tagging")]
+use super::replaygain::ReplayGain;
+use super::{
+    models::{Album, Track},
+    mp3,
+};
 
-pub fn prepare_directory(path: Option<&PathBuf>, album: &Album) -> Result<PathBuf> {
-    let path = match path {
+/// Directory where tracks of `album` are saved to, ie: `path/artist/album`.
+#[must_use]
+pub fn album_directory(path: Option<&PathBuf>, album: &Album) -> PathBuf {
+    match path {
         Some(path) => Path::new(path).join(&album.artist),
         None => PathBuf::from(&album.artist),
     }
-    .join(&album.album);
+    .join(&album.album)
+}
+
+pub fn prepare_directory(path: Option<&PathBuf>, album: &Album) -> Result<PathBuf> {
+    let path = album_directory(path, album);
 
     if !path.exists() {
         fs::create_dir_all(&path)?;
@@ -27,124 +45,572 @@ pub fn prepare_directory(path: Option<&PathBuf>, album: &Album) -> Result<PathBu
     Ok(path)
 }
 
+/// What to do when a track's file already exists on disk.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OnExists {
+    /// Leave the existing file alone
+    #[default]
+    Skip,
+    /// Download again and replace the existing file
+    Overwrite,
+    /// Download to the next free `name (n).mp3`
+    Rename,
+    /// Download again only if the existing file looks incomplete or mismatched
+    Verify,
+}
+
 #[must_use]
-pub fn make_path(track: &Track, root: &Path, track_format: &String) -> PathBuf {
+pub fn make_path(album: &Album, track: &Track, root: &Path, track_format: &str) -> PathBuf {
     let file_name = if track_format.is_empty() {
         format!("{} - {}", &track.num, &track.name)
     } else {
-        parse_track_template(track_format, track)
+        parse_track_template(track_format, album, track)
     };
 
     root.join(file_name).with_extension("mp3")
 }
 
-pub fn track_path(track: &Track, root: &Path, track_format: &String) -> Result<PathBuf> {
-    let file_name = if track_format.is_empty() {
-        format!("{} - {}", &track.num, &track.name)
-    } else {
-        parse_track_template(track_format, track)
+/// Path the track should be downloaded to according to `on_exists`,
+/// `None` means the track should not be downloaded at all.
+#[must_use]
+pub fn track_path(
+    album: &Album,
+    track: &Track,
+    root: &Path,
+    track_format: &str,
+    on_exists: OnExists,
+) -> Option<PathBuf> {
+    let file = make_path(album, track, root, track_format);
+
+    if !file.exists() {
+        return Some(file);
+    }
+
+    match on_exists {
+        OnExists::Skip => None,
+        OnExists::Overwrite => Some(file),
+        OnExists::Rename => Some(next_free_path(&file)),
+        OnExists::Verify => {
+            if is_intact(album, track, &file) {
+                None
+            } else {
+                Some(file)
+            }
+        }
+    }
+}
+
+/// Find first `name (n).mp3` next to `file` that does not exist yet, starting from 2.
+fn next_free_path(file: &Path) -> PathBuf {
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+
+    (2..)
+        .map(|n| file.with_file_name(format!("{stem} ({n}).mp3")))
+        .find(|path| !path.exists())
+        .expect("a free file name")
+}
+
+/// Check that an existing file looks like a complete download of `track`:
+/// its MPEG stream must be well-formed and as long as the track, and its title, album &
+/// track number must match.
+#[must_use]
+pub fn is_intact(album: &Album, track: &Track, path: &Path) -> bool {
+    let Ok(data) = fs::read(path) else {
+        return false;
     };
 
-    let file = root.join(file_name).with_extension("mp3");
+    mp3::verify(&data, track.duration).is_ok() && tags_match(album, track, path)
+}
 
-    if file.exists() {
-        bail!("{} already exists", file.display())
-    }
+#[cfg(feature = "tagging")]
+fn tags_match(album: &Album, track: &Track, path: &Path) -> bool {
+    let Ok(tag) = Tag::read_from_path(path) else {
+        return false;
+    };
+
+    tag.title() == Some(track.name.as_str())
+        && tag.album() == Some(album.album.as_str())
+        && tag.track() == Some(track.num as u32)
+}
 
-    Ok(file)
+/// Without tagging, files can only be checked by their MPEG stream.
+#[cfg(not(feature = "tagging"))]
+fn tags_match(_: &Album, _: &Track, _: &Path) -> bool {
+    true
 }
 
+/// Parse any of the date formats found on bandcamp, eg: `28 Sep 2014 04:19:31 GMT`,
+/// `released September 28, 2014` or `2014-09-28T04:19:31Z`.
+#[must_use]
+pub fn parse_date(date: &str) -> Option<NaiveDateTime> {
+    const DATE_TIMES: [&str; 6] = [
+        "%d %b %Y %T",
+        "%a, %d %b %Y %T",
+        "%Y-%m-%dT%T%.f%#z",
+        "%Y-%m-%dT%T%#z",
+        "%Y-%m-%dT%T",
+        "%Y-%m-%d %T",
+    ];
+    const DATES: [&str; 5] = ["%B %d, %Y", "%b %d, %Y", "%d %B %Y", "%d %b %Y", "%Y-%m-%d"];
+
+    let date = date.trim();
+    // upcoming releases read `releases October 3, 2024`
+    let date = ["released ", "releases "]
+        .iter()
+        .find_map(|prefix| date.strip_prefix(prefix))
+        .unwrap_or(date);
+    let date = [" GMT", " UTC", " +0000"]
+        .iter()
+        .find_map(|suffix| date.strip_suffix(suffix))
+        .unwrap_or(date);
+
+    DATE_TIMES
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(date, format).ok())
+        .or_else(|| {
+            DATES
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(date, format).ok())
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+}
+
+/// ID3 timestamp of `date`, time is left out when it's midnight as bandcamp
+/// mostly only knows the day of a release.
+#[cfg(feature = "tagging")]
+#[must_use]
+pub fn id3_timestamp(date: NaiveDateTime) -> Timestamp {
+    let time = (date.time() != NaiveTime::MIN).then_some(date.time());
+
+    Timestamp {
+        year: date.year(),
+        month: Some(date.month() as u8),
+        day: Some(date.day() as u8),
+        hour: time.map(|time| time.hour() as u8),
+        minute: time.map(|time| time.minute() as u8),
+        second: time.map(|time| time.second() as u8),
+    }
+}
+
+#[cfg(feature = "tagging")]
 #[must_use]
 pub fn timestamp(date_string: &str) -> Option<Timestamp> {
-    use chrono::{DateTime, Datelike, Timelike};
-
-    // if String looks like this `28 Sep 2014 04:19:31 GMT`
-    match DateTime::parse_from_str(date_string, "%d %b %Y %T %Z") {
-        Ok(date) => Some(Timestamp {
-            year: date.year(),
-            month: Some(date.month() as u8),
-            day: Some(date.day() as u8),
-            hour: Some(date.hour() as u8),
-            minute: Some(date.minute() as u8),
-            second: Some(date.second() as u8),
-        }),
-        Err(_) => {
-            // if String looks like this `released September 28, 2014`
-            if date_string.starts_with("released ") {
-                let mut date_string = date_string.to_owned();
-                date_string.push_str(" 01:01:01");
-
-                match DateTime::parse_from_str(&date_string, "released %B %d, %Y %T") {
-                    Ok(date) => Some(Timestamp {
-                        year: date.year(),
-                        month: Some(date.month() as u8),
-                        day: Some(date.day() as u8),
-                        hour: Some(date.hour() as u8),
-                        minute: Some(date.minute() as u8),
-                        second: Some(date.second() as u8),
-                    }),
-                    Err(_) => None,
-                }
-            } else {
-                None
-            }
-        }
+    parse_date(date_string).map(id3_timestamp)
+}
+
+/// Parse an ISO 8601 duration, as found in `application/ld+json`, eg: `P00H03M21S` or `PT3M21.5S`.
+#[must_use]
+pub fn parse_duration(duration: &str) -> Option<Duration> {
+    let mut rest = duration.strip_prefix('P')?;
+    let mut secs = 0.0;
+    let mut found = false;
+
+    while !rest.is_empty() {
+        rest = rest.strip_prefix('T').unwrap_or(rest);
+
+        let end = rest.find(|c: char| c.is_ascii_alphabetic())?;
+        let value = rest[..end].parse::<f64>().ok()?;
+
+        secs += value
+            * match &rest[end..=end] {
+                "D" => 86400.0,
+                "H" => 3600.0,
+                "M" => 60.0,
+                "S" => 1.0,
+                _ => return None,
+            };
+
+        found = true;
+        rest = &rest[end + 1..];
+    }
+
+    if !found || secs <= 0.0 {
+        return None;
+    }
+
+    Duration::try_from_secs_f64(secs).ok()
+}
+
+/// Format a duration as `m:ss` or `h:mm:ss`.
+#[must_use]
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{secs:02}")
+    } else {
+        format!("{minutes}:{secs:02}")
     }
 }
 
+/// Keys available to track templates, with their values for `track`.
 #[must_use]
-pub fn format_container(
-    num: &str,
-    track: &str,
-    album: &str,
-    artist: &str,
-) -> HashMap<String, String> {
+pub fn format_container(album: &Album, track: &Track) -> HashMap<String, String> {
     HashMap::from([
-        ("num".to_string(), num.to_owned()),
-        ("track".to_string(), track.to_owned()),
-        ("album".to_string(), album.to_owned()),
-        ("artist".to_string(), artist.to_owned()),
+        ("num".to_string(), track.num.to_string()),
+        ("track".to_string(), track.name.clone()),
+        ("album".to_string(), album.album.clone()),
+        ("artist".to_string(), album.artist.clone()),
+        (
+            "track_artist".to_string(),
+            track.artist_name(album).to_owned(),
+        ),
+        ("label".to_string(), album.label.clone().unwrap_or_default()),
+        (
+            "catalog".to_string(),
+            album.catalog.clone().unwrap_or_default(),
+        ),
+        (
+            "release_type".to_string(),
+            album
+                .release_type
+                .map(|kind| kind.to_string())
+                .unwrap_or_default(),
+        ),
     ])
 }
 
 #[must_use]
-pub fn parse_track_template(format: &str, track: &Track) -> String {
-    let Album { album, artist, .. } = &track.album;
-    let Track { ref num, name, .. } = track;
-
-    let vars = format_container(&num.to_string(), name, album, artist);
+pub fn parse_track_template(format: &str, album: &Album, track: &Track) -> String {
+    let vars = format_container(album, track);
 
     strfmt(format, &vars).expect("failed to format keys")
 }
 
+/// Which tags [`tag_mp3`] writes and how.
+#[cfg(feature = "tagging")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagProfile {
+    /// ID3v2 version to write, some older players can only read `Id3v23`
+    pub version: Version,
+    /// Also write an ID3v1 tag at the end of the file
+    pub id3v1: bool,
+    pub lyrics: bool,
+    /// ISO 639-2 code of the lyrics' language
+    pub lyrics_lang: String,
+    pub pictures: bool,
+    /// "about" & "credits" comments
+    pub comments: bool,
+    /// Largest width/height of embedded album art in pixels
+    pub max_art_size: Option<u32>,
+    /// Also write lyrics to a file next to the track
+    pub lyrics_file: Option<LyricsFile>,
+    /// Analyze downloaded tracks and tag them with their ReplayGain
+    pub replay_gain: bool,
+    /// Which of the album's keywords are written as genres
+    pub genre: Genre,
+    /// Keywords to keep as genres, and how to rename them
+    pub genre_map: Option<GenreMap>,
+}
+
+/// Which of the album's keywords [`tag_mp3`] writes as genres.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Genre {
+    /// Only the first keyword, usually the main genre
+    First,
+    /// Every keyword, as a multi-valued genre frame
+    #[default]
+    All,
+}
+
+/// Allowlist of keywords to write as genres, with optional renaming.
+///
+/// Parsed from lines like `keyword` or `keyword = Genre`, blank lines & lines starting
+/// with `#` are ignored. Keywords are matched case-insensitively.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GenreMap(HashMap<String, String>);
+
+impl GenreMap {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut map = HashMap::new();
+
+        for (num, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (keyword, genre) = line.split_once('=').unwrap_or((line, line));
+            let (keyword, genre) = (keyword.trim(), genre.trim());
+
+            if keyword.is_empty() || genre.is_empty() {
+                anyhow::bail!("line {}: expected `keyword` or `keyword = Genre`", num + 1);
+            }
+
+            map.insert(keyword.to_lowercase(), genre.to_string());
+        }
+
+        Ok(Self(map))
+    }
+
+    /// Genre `keyword` maps to, `None` if it isn't allowed.
+    #[must_use]
+    pub fn get(&self, keyword: &str) -> Option<&str> {
+        self.0.get(&keyword.to_lowercase()).map(String::as_str)
+    }
+}
+
+/// Format of lyrics files written next to tracks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LyricsFile {
+    /// Plain text, `<track>.txt`
+    Txt,
+    /// Unsynced LRC with title/artist/album headers, `<track>.lrc`
+    Lrc,
+}
+
+impl LyricsFile {
+    /// Path of the lyrics file of the track at `track_path`.
+    #[must_use]
+    pub fn path(self, track_path: &Path) -> PathBuf {
+        match self {
+            Self::Txt => track_path.with_extension("txt"),
+            Self::Lrc => track_path.with_extension("lrc"),
+        }
+    }
+}
+
+#[cfg(feature = "tagging")]
+impl Default for TagProfile {
+    fn default() -> Self {
+        Self {
+            version: Version::Id3v24,
+            id3v1: false,
+            lyrics: true,
+            lyrics_lang: "eng".to_string(),
+            pictures: true,
+            comments: true,
+            max_art_size: None,
+            lyrics_file: None,
+            replay_gain: false,
+            genre: Genre::All,
+            genre_map: None,
+        }
+    }
+}
+
+#[cfg(feature = "tagging")]
+impl TagProfile {
+    /// Genres to tag `album`'s tracks with, from its keywords.
+    #[must_use]
+    pub fn genres(&self, album: &Album) -> Vec<String> {
+        let mut genres = Vec::<String>::new();
+
+        for keyword in &album.tags {
+            let genre = match &self.genre_map {
+                Some(map) => match map.get(keyword) {
+                    Some(genre) => genre,
+                    None => continue,
+                },
+                None => keyword,
+            };
+
+            if !genres.iter().any(|known| known.eq_ignore_ascii_case(genre)) {
+                genres.push(genre.to_string());
+            }
+        }
+
+        if self.genre == Genre::First {
+            genres.truncate(1);
+        }
+
+        genres
+    }
+
+    /// Url of the album art to embed, `None` if pictures are disabled.
+    ///
+    /// Bandcamp serves album art in a fixed set of square sizes (the `_N` suffix of its url),
+    /// with `max_art_size` the largest one that fits is picked.
+    #[must_use]
+    pub fn art_url(&self, url: &str) -> Option<String> {
+        // (size in pixels, bandcamp's format id), largest first
+        const SIZES: [(u32, u32); 7] = [
+            (1200, 10),
+            (700, 16),
+            (350, 2),
+            (300, 4),
+            (210, 9),
+            (150, 7),
+            (100, 3),
+        ];
+
+        if !self.pictures || url.is_empty() {
+            return None;
+        }
+
+        let Some(max_size) = self.max_art_size else {
+            return Some(url.to_string());
+        };
+
+        let format = SIZES
+            .iter()
+            .find(|(size, _)| *size <= max_size)
+            .map_or(SIZES[SIZES.len() - 1].1, |(_, format)| *format);
+
+        match url.rsplit_once('_') {
+            Some((base, suffix)) if suffix.starts_with(|c: char| c.is_ascii_digit()) => {
+                let ext = suffix.trim_start_matches(|c: char| c.is_ascii_digit());
+
+                Some(format!("{base}_{format}{ext}"))
+            }
+            _ => Some(url.to_string()),
+        }
+    }
+}
+
+/// Write lyrics of the track at `path` to a file next to it, as per `profile.lyrics_file`.
+///
+/// Returns path of the written file, `None` if it is disabled or the track has no lyrics.
+#[cfg(feature = "tagging")]
+pub fn write_lyrics_file(
+    album: &Album,
+    track: &Track,
+    path: &Path,
+    profile: &TagProfile,
+) -> Result<Option<PathBuf>> {
+    let (Some(format), Some(lyrics)) = (profile.lyrics_file, &track.lyrics) else {
+        return Ok(None);
+    };
+
+    let file = format.path(path);
+
+    let content = match format {
+        LyricsFile::Txt => format!("{lyrics}\n"),
+        LyricsFile::Lrc => {
+            let mut content = format!(
+                "[ti:{}]\n[ar:{}]\n[al:{}]\n",
+                track.name, album.artist, album.album
+            );
+
+            for line in lyrics.lines() {
+                content.push_str(line.trim_end());
+                content.push('\n');
+            }
+
+            content
+        }
+    };
+
+    fs::write(&file, content)?;
+
+    Ok(Some(file))
+}
+
+/// Write tags of `album`'s `track` to the file at `path`.
+///
+/// Frames that are not written by sunny (eg: ratings or custom comments) are preserved,
+/// which makes it safe to call on files that were already tagged.
+#[cfg(feature = "tagging")]
 pub fn tag_mp3(
     album_art: Option<Vec<u8>>,
-    release_date: Option<id3::Timestamp>,
+    album: &Album,
     track: &Track,
     path: &Path,
+    profile: &TagProfile,
 ) -> Result<()> {
-    let album = &track.album;
-    let mut tag = Tag::new();
+    let mut tag = Tag::read_from_path(path).unwrap_or_else(|_| Tag::new());
 
     tag.set_title(&*track.name);
     tag.set_track(track.num as u32);
     tag.set_album(&album.album);
-    tag.set_artist(&album.artist);
+    tag.set_artist(track.artist_name(album));
     tag.set_album_artist(&album.artist);
 
-    if let Some(ref lyrics) = track.lyrics {
+    tag.remove_duration();
+
+    if let Some(duration) = track.duration {
+        tag.set_duration(duration.as_millis() as u32);
+    }
+
+    tag.remove_total_tracks();
+
+    if album.num_tracks > 0 {
+        tag.set_total_tracks(album.num_tracks);
+    }
+
+    tag.remove("TPUB");
+
+    if let Some(ref label) = album.label {
+        tag.set_text("TPUB", label);
+    }
+
+    tag.remove("WOAS");
+    tag.remove("WOAF");
+
+    if !album.url.is_empty() {
+        tag.add_frame(Frame::link("WOAS", &album.url));
+    }
+
+    if let Some(ref page_url) = track.page_url {
+        tag.add_frame(Frame::link("WOAF", page_url));
+    }
+
+    for (description, text) in [("about", &album.about), ("credits", &album.credits)] {
+        tag.remove_comment(Some(description), None);
+
+        if let (true, Some(text)) = (profile.comments, text) {
+            tag.add_frame(Comment {
+                lang: "eng".to_string(),
+                description: description.to_string(),
+                text: text.to_string(),
+            });
+        }
+    }
+
+    for (description, value) in [
+        ("BANDCAMP_ALBUM_ID", album.id.map(|id| id.to_string())),
+        ("BANDCAMP_TRACK_ID", track.id.map(|id| id.to_string())),
+        ("CATALOGNUMBER", album.catalog.clone()),
+        (
+            "RELEASETYPE",
+            album.release_type.map(|kind| kind.to_string()),
+        ),
+    ] {
+        tag.remove_extended_text(Some(description), None);
+
+        if let Some(value) = value {
+            tag.add_frame(ExtendedText {
+                description: description.to_string(),
+                value,
+            });
+        }
+    }
+
+    // only sunny's lyrics are replaced, others may be translations or added by the user
+    for frame in tag.remove("USLT") {
+        let ours = frame.content().lyrics().is_some_and(|lyrics| {
+            lyrics.lang == profile.lyrics_lang && lyrics.description.is_empty()
+        });
+
+        if !ours {
+            tag.add_frame(frame);
+        }
+    }
+
+    if let (true, Some(lyrics)) = (profile.lyrics, &track.lyrics) {
         tag.add_frame(Lyrics {
-            lang: "eng".to_string(),
+            lang: profile.lyrics_lang.clone(),
             description: String::with_capacity(0),
             text: String::from(lyrics.as_str()),
         });
     }
 
-    if let Some(tags) = &album.tags {
-        tag.set_genre(tags.to_string());
+    tag.remove_genre();
+
+    let genres = profile.genres(album);
+
+    if !genres.is_empty() {
+        tag.set_text_values("TCON", genres);
     }
 
-    if let Some(album_art) = album_art {
+    // a cover that failed to be fetched keeps the current one
+    if !profile.pictures || album_art.is_some() {
+        tag.remove_picture_by_type(PictureType::CoverFront);
+    }
+
+    if let (true, Some(album_art)) = (profile.pictures, album_art) {
         tag.add_frame(Picture {
             mime_type: "image/jpeg".to_string(),
             picture_type: PictureType::CoverFront,
@@ -153,46 +619,129 @@ pub fn tag_mp3(
         });
     }
 
-    if let Some(ts) = release_date {
-        tag.set_date_recorded(ts);
+    tag.remove_date_recorded();
+    tag.remove_date_released();
+
+    for id in ["TYER", "TDAT", "TIME"] {
+        tag.remove(id);
     }
 
-    tag.write_to_path(path, Version::Id3v24)?;
+    match (album.released, profile.version) {
+        // TDRC & TDRL are ID3v2.4 frames, ID3v2.3 splits dates in year, DDMM & HHMM
+        (Some(date), Version::Id3v23) => {
+            tag.set_year(date.year());
+            tag.set_text("TDAT", format!("{:02}{:02}", date.day(), date.month()));
 
-    Ok(())
-}
+            if date.time() != NaiveTime::MIN {
+                tag.set_text("TIME", format!("{:02}{:02}", date.hour(), date.minute()));
+            }
+        }
+        (Some(date), _) => {
+            let ts = id3_timestamp(date);
 
-pub fn print_as_tree(albums: &[Album]) {
-    if albums.is_empty() {
-        println!("Noting to print");
-    } else {
-        let artist = &albums[0].artist;
+            tag.set_date_recorded(ts);
+            tag.set_date_released(ts);
+        }
+        (None, _) => {}
+    }
 
-        println!("{artist}");
+    write_tag(&tag, path, profile)
+}
 
-        for (album_index, album) in albums.iter().enumerate() {
-            let next_album = albums.get(album_index + 1);
+/// Add ReplayGain `TXXX` frames to the tag of the file at `path`, album frames are only
+/// written once they are known.
+#[cfg(feature = "tagging")]
+pub fn tag_replay_gain(gain: &ReplayGain, path: &Path, profile: &TagProfile) -> Result<()> {
+    let mut tag = Tag::read_from_path(path).unwrap_or_else(|_| Tag::new());
+
+    let frames = [
+        (
+            "REPLAYGAIN_TRACK_GAIN",
+            Some(format!("{:.2} dB", gain.track_gain)),
+        ),
+        (
+            "REPLAYGAIN_TRACK_PEAK",
+            Some(format!("{:.6}", gain.track_peak)),
+        ),
+        (
+            "REPLAYGAIN_ALBUM_GAIN",
+            gain.album_gain.map(|gain| format!("{gain:.2} dB")),
+        ),
+        (
+            "REPLAYGAIN_ALBUM_PEAK",
+            gain.album_peak.map(|peak| format!("{peak:.6}")),
+        ),
+    ];
+
+    for (description, value) in frames {
+        if let Some(value) = value {
+            tag.remove_extended_text(Some(description), None);
+            tag.add_frame(ExtendedText {
+                description: description.to_string(),
+                value,
+            });
+        }
+    }
 
-            let padding = if next_album.is_some() {
-                "├──"
-            } else {
-                "└──"
-            };
+    write_tag(&tag, path, profile)
+}
 
-            println!("  {padding} {}", album.album);
+/// Write `tag` to the file at `path`, replacing its ID3 tags.
+#[cfg(feature = "tagging")]
+fn write_tag(tag: &Tag, path: &Path, profile: &TagProfile) -> Result<()> {
+    id3::v1v2::write_to_path(path, tag, profile.version)?;
 
-            for (track_index, track) in album.tracks.iter().enumerate() {
-                let next_track = album.tracks.get(track_index + 1);
-                let bar = if next_album.is_some() { "│" } else { " " };
+    if profile.id3v1 {
+        write_id3v1(tag, path)?;
+    }
 
-                let padding = if next_track.is_some() {
-                    format!("  {bar}   ├──")
-                } else {
-                    format!("  {bar}   └──")
-                };
+    Ok(())
+}
 
-                println!("{padding} {}", track.name);
-            }
-        }
+/// Append an ID3v1.1 tag built from `tag` to the file at `path`, which must not already have one.
+#[cfg(feature = "tagging")]
+fn write_id3v1(tag: &Tag, path: &Path) -> Result<()> {
+    // fixed size fields, latin-1 encoded and padded with zeros
+    fn field(text: Option<&str>, len: usize) -> Vec<u8> {
+        let mut bytes = text
+            .unwrap_or_default()
+            .chars()
+            .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+            .take(len)
+            .collect::<Vec<_>>();
+
+        bytes.resize(len, 0);
+        bytes
     }
+
+    let year = tag
+        .date_released()
+        .or_else(|| tag.date_recorded())
+        .map(|date| date.year)
+        .or_else(|| tag.year())
+        .map(|year| year.to_string());
+
+    let mut v1 = Vec::with_capacity(128);
+
+    v1.extend_from_slice(b"TAG");
+    v1.extend(field(tag.title(), 30));
+    v1.extend(field(tag.artist(), 30));
+    v1.extend(field(tag.album(), 30));
+    v1.extend(field(year.as_deref(), 4));
+    v1.extend(field(None, 28));
+    v1.push(0);
+    v1.push(
+        tag.track()
+            .and_then(|num| u8::try_from(num).ok())
+            .unwrap_or(0),
+    );
+    // genre: unknown, free form genres can't be represented
+    v1.push(255);
+
+    fs::OpenOptions::new()
+        .append(true)
+        .open(path)?
+        .write_all(&v1)?;
+
+    Ok(())
 }
@@ -1,11 +1,19 @@
 mod cli;
+mod output;
+mod progress;
 
-use std::process::exit;
+use std::{
+    cmp::Reverse,
+    path::{Path, PathBuf},
+    process::exit,
+};
 
+use console::style;
 use sunny::{
-    client,
-    spider::{fetch_albums, search as Search},
-    utils::{prepare_directory, print_as_tree},
+    library,
+    models::Album,
+    utils::{album_directory, format_duration, track_path, OnExists, TagProfile},
+    Sunny,
 };
 
 fn main() {
@@ -34,56 +42,296 @@ fn app_main() -> anyhow::Result<()> {
         path,
         url,
         track_format,
-        // dry_run,
+        dry_run,
         skip_albums,
+        release_type,
+        since,
+        until,
+        order,
+        on_exists,
+        overrides,
+        tagging,
         list_available,
         search,
         r#type,
+        relevance,
+        output,
+        pick,
+        select,
+        command,
         ..
     } = cli::Config::default();
 
-    if search {
-        Search(&url, r#type.as_search_filter())?;
+    let mut builder = Sunny::builder()
+        .on_exists(on_exists.as_policy())
+        .profile(tagging.as_profile())
+        .on_event({
+            let progress = progress::Progress::default();
+            move |event| progress.handle(event)
+        });
 
-        return Ok(());
+    if let Some(path) = &path {
+        builder = builder.path(path);
+    }
+
+    if let Some(track_format) = &track_format {
+        builder = builder.track_format(track_format);
+    }
+
+    if let Some(overrides) = overrides {
+        builder = builder.overrides(overrides);
+    }
+
+    let sunny = builder.build();
+
+    match command {
+        Some(cli::Command::Retag { url }) => {
+            return retag(&sunny, &url);
+        }
+        Some(cli::Command::Reorganize { dry_run }) => {
+            return reorganize(path.as_ref(), track_format.as_ref(), dry_run);
+        }
+        Some(cli::Command::Verify { dir }) => {
+            return verify(&dir);
+        }
+        None => {}
     }
 
-    let url = parse_url(&url)?;
+    let url = url.expect("url to be required without a subcommand");
+
+    let url = if search {
+        let mut results = sunny.search(&url, r#type.as_search_filter())?;
+
+        results.retain(|item| r#type.matches(item.kind));
+
+        if results.is_empty() && pick.is_none() {
+            // scripts still get a valid document, eg: `[]` or a csv header
+            if !matches!(output, cli::Output::Table) {
+                return output::print_search_results(&results, output, false);
+            }
+
+            eprintln!("No matching results\nTry a different `--type` or a new search keyword.");
+            return Ok(());
+        }
+
+        if !relevance {
+            results.sort_by_key(|item| item.name.to_lowercase());
+        }
+
+        if matches!(output, cli::Output::Table) {
+            // tables are printed per kind, numbers must follow the same order
+            results.sort_by_key(|item| item.kind);
+        }
+
+        let picked = match pick {
+            Some(num) => match results.get(num.get() - 1) {
+                Some(item) => item,
+                None => anyhow::bail!(
+                    "--pick {num} is out of range, found {} results",
+                    results.len()
+                ),
+            },
+            None => {
+                output::print_search_results(&results, output, select)?;
+
+                if !select {
+                    return Ok(());
+                }
+
+                match output::prompt_pick(results.len())? {
+                    Some(index) => &results[index],
+                    None => return Ok(()),
+                }
+            }
+        };
+
+        eprintln!("Downloading {} `{}`", picked.kind, picked.name);
+
+        picked.download_url()
+    } else {
+        parse_url(&url)?
+    };
 
-    let albums = fetch_albums(&url)?;
+    let mut albums = sunny.fetch(&url)?;
 
     if list_available {
-        print_as_tree(&albums);
-        return Ok(());
+        return output::print_albums(
+            &albums,
+            output,
+            path.as_ref(),
+            track_format.as_deref().unwrap_or_default(),
+        );
     }
 
-    let tracks = albums
-        .iter()
-        .filter(|album| {
-            skip_albums
-                .as_ref()
-                .map_or(true, |to_skip| !to_skip.contains(&album.album))
-        })
-        .flat_map(|album| {
-            let root =
-                prepare_directory(path.as_ref(), album).expect("root directory to be created");
-
-            album
-                .tracks
-                .iter()
-                .filter(|track| {
-                    if track.url.is_empty() {
-                        eprintln!("No url found for `{}`, skipping.", track.name);
-                        false
-                    } else {
-                        true
-                    }
-                })
-                .map(move |track| (track, root.clone()))
+    albums.retain(|album| {
+        skip_albums
+            .as_ref()
+            .is_none_or(|to_skip| !to_skip.contains(&album.album))
+    });
+
+    albums.retain(|album| {
+        let (Some(kinds), Some(kind)) = (&release_type, album.release_type) else {
+            return true;
+        };
+
+        kinds.iter().any(|wanted| wanted.as_release_type() == kind)
+    });
+
+    // albums without a known release date are kept, like unknown release types
+    albums.retain(|album| {
+        album.released.is_none_or(|released| {
+            since.is_none_or(|since| released.date() >= since)
+                && until.is_none_or(|until| released.date() <= until)
         })
-        .collect();
+    });
+
+    match order {
+        // albums without a known release date come last either way
+        Some(cli::Order::Newest) => {
+            albums.sort_by_key(|album| (album.released.is_none(), Reverse(album.released)));
+        }
+        Some(cli::Order::Oldest) => {
+            albums.sort_by_key(|album| (album.released.is_none(), album.released));
+        }
+        None => {}
+    }
+
+    if dry_run {
+        print_dry_run(
+            &albums,
+            path.as_ref(),
+            track_format.as_ref(),
+            on_exists.as_policy(),
+            &tagging.as_profile(),
+        );
+        return Ok(());
+    }
+
+    sunny.download(&albums)
+}
+
+fn print_dry_run(
+    albums: &[Album],
+    path: Option<&PathBuf>,
+    track_format: Option<&String>,
+    on_exists: OnExists,
+    profile: &TagProfile,
+) {
+    let track_format = track_format.map_or("", String::as_str);
+
+    for album in albums {
+        let root = album_directory(path, album);
 
-    client::MultiDownloader::run(tracks, track_format.as_ref())?;
+        for track in &album.tracks {
+            if track.url.is_empty() {
+                eprintln!("No url found for `{}`, skipping.", track.name);
+                continue;
+            }
+
+            let Some(path) = track_path(album, track, &root, track_format, on_exists) else {
+                println!("`{}` already exists, skipping", track.name);
+                continue;
+            };
+
+            println!("{}", path.display());
+
+            if let (Some(format), Some(_)) = (profile.lyrics_file, &track.lyrics) {
+                println!("{}", format.path(&path).display());
+            }
+        }
+    }
+}
+
+fn retag(sunny: &Sunny, url: &str) -> anyhow::Result<()> {
+    let url = parse_url(url)?;
+
+    for album in sunny.fetch(&url)? {
+        for (track, ret) in sunny.retag(&album) {
+            match ret {
+                Ok(Some(path)) => println!("{} {}", path.display(), style("✔").green()),
+                Ok(None) => eprintln!("`{}` is not downloaded, skipping", track.name),
+                Err(err) => eprintln!("E: {err} - `{}`", track.name),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn reorganize(
+    path: Option<&PathBuf>,
+    track_format: Option<&String>,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let root = path.cloned().unwrap_or_else(|| PathBuf::from("."));
+    let track_format = track_format.map_or("", String::as_str);
+
+    let moves = library::plan_reorganize(&root, track_format)?;
+
+    if moves.is_empty() {
+        println!("Nothing to move");
+        return Ok(());
+    }
+
+    for item in moves.iter().filter(|item| item.conflict.is_some()) {
+        eprintln!(
+            "{} {}: {}",
+            style("✘").red(),
+            item.from.display(),
+            item.conflict.as_deref().unwrap_or_default()
+        );
+    }
+
+    if dry_run {
+        for item in moves.iter().filter(|item| item.conflict.is_none()) {
+            println!("{} -> {}", item.from.display(), item.to.display());
+        }
+
+        return Ok(());
+    }
+
+    for moved in library::reorganize(&moves, &root) {
+        let item = moved.item;
+
+        match moved.track {
+            Ok(()) => println!(
+                "{} -> {} {}",
+                item.from.display(),
+                item.to.display(),
+                style("✔").green()
+            ),
+            Err(err) => eprintln!("E: {err} - `{}`", item.from.display()),
+        }
+
+        for (lyrics, err) in moved.lyrics {
+            eprintln!("E: {err} - `{}`", lyrics.display());
+        }
+    }
+
+    Ok(())
+}
+
+fn verify(dir: &Path) -> anyhow::Result<()> {
+    let mut failures = 0;
+
+    for (path, ret) in library::verify(dir)? {
+        match ret {
+            Ok(stream) => println!(
+                "{} {} ({})",
+                path.display(),
+                style("✔").green(),
+                format_duration(stream.duration)
+            ),
+            Err(err) => {
+                failures += 1;
+                eprintln!("{} {}: {err}", style("✘").red(), path.display());
+            }
+        }
+    }
+
+    if failures > 0 {
+        anyhow::bail!("{failures} file(s) failed verification");
+    }
 
     Ok(())
 }
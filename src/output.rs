@@ -0,0 +1,430 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use anyhow::Result;
+use console::style;
+use serde::Serialize;
+use sunny::{
+    models::{Album, SearchKind, SearchResult, Track},
+    utils::{album_directory, format_duration, make_path},
+};
+
+use crate::cli::Output;
+
+/// Columns of machine-readable search results.
+const SEARCH_COLUMNS: [&str; 9] = [
+    "kind",
+    "name",
+    "band_name",
+    "album_name",
+    "url",
+    "location",
+    "genre",
+    "art_url",
+    "id",
+];
+
+fn search_fields(item: &SearchResult) -> [Option<String>; 9] {
+    [
+        Some(item.kind.to_string()),
+        Some(item.name.clone()),
+        item.band_name.clone(),
+        item.album_name.clone(),
+        Some(item.url.clone()),
+        item.location.clone(),
+        item.genre.clone(),
+        item.art_url.clone(),
+        item.id.map(|id| id.to_string()),
+    ]
+}
+
+/// Quote `field` as per RFC 4180 when needed.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Tabs & newlines can't be escaped in TSV, they are replaced by spaces.
+fn tsv_field(field: &str) -> String {
+    field.replace(['\t', '\n', '\r'], " ")
+}
+
+fn write_rows<const N: usize>(
+    out: &mut impl Write,
+    header: [&str; N],
+    rows: impl Iterator<Item = [Option<String>; N]>,
+    separator: &str,
+    escape: fn(&str) -> String,
+) -> io::Result<()> {
+    writeln!(out, "{}", header.join(separator))?;
+
+    for row in rows {
+        let row = row.map(|field| escape(field.as_deref().unwrap_or_default()));
+
+        writeln!(out, "{}", row.join(separator))?;
+    }
+
+    Ok(())
+}
+
+/// Machine-readable formats of `--output`.
+#[derive(Clone, Copy)]
+enum Records {
+    Json,
+    Ndjson,
+    Yaml,
+    Csv,
+    Tsv,
+}
+
+impl Records {
+    /// `None` for tables, which each caller prints its own way.
+    const fn of(output: Output) -> Option<Self> {
+        match output {
+            Output::Table => None,
+            Output::Json => Some(Self::Json),
+            Output::Ndjson => Some(Self::Ndjson),
+            Output::Yaml => Some(Self::Yaml),
+            Output::Csv => Some(Self::Csv),
+            Output::Tsv => Some(Self::Tsv),
+        }
+    }
+}
+
+/// Print records in a machine-readable `format`, as `items` for structured formats
+/// (serialized as is, so they can be read back as models with the `serde` feature)
+/// or as `rows` under `header` for tabular ones.
+fn print_records<const N: usize>(
+    format: Records,
+    items: &[impl Serialize],
+    header: [&str; N],
+    rows: impl Iterator<Item = [Option<String>; N]>,
+) -> Result<()> {
+    let mut out = io::stdout().lock();
+
+    match format {
+        Records::Json => {
+            serde_json::to_writer_pretty(&mut out, items)?;
+            writeln!(out)?;
+        }
+        Records::Ndjson => {
+            for item in items {
+                serde_json::to_writer(&mut out, item)?;
+                writeln!(out)?;
+            }
+        }
+        Records::Yaml => serde_yaml::to_writer(&mut out, items)?,
+        Records::Csv => write_rows(&mut out, header, rows, ",", csv_field)?,
+        Records::Tsv => write_rows(&mut out, header, rows, "\t", tsv_field)?,
+    }
+
+    Ok(())
+}
+
+/// Print `results` in the `output` format, `numbered` tables are meant for picking a result.
+pub fn print_search_results(
+    results: &[SearchResult],
+    output: Output,
+    numbered: bool,
+) -> Result<()> {
+    let Some(format) = Records::of(output) else {
+        print_table(results, numbered);
+        return Ok(());
+    };
+
+    print_records(
+        format,
+        results,
+        SEARCH_COLUMNS,
+        results.iter().map(search_fields),
+    )
+}
+
+/// Columns of machine-readable listings, one row per track.
+const LIST_COLUMNS: [&str; 17] = [
+    "album_artist",
+    "album",
+    "album_url",
+    "album_id",
+    "release_date",
+    "release_type",
+    "label",
+    "catalog",
+    "genres",
+    "art_url",
+    "num",
+    "track",
+    "track_artist",
+    "duration",
+    "stream_url",
+    "track_url",
+    "track_id",
+];
+
+fn track_fields(album: &Album, track: &Track) -> [Option<String>; 17] {
+    [
+        Some(album.artist.clone()),
+        Some(album.album.clone()),
+        Some(album.url.clone()),
+        album.id.map(|id| id.to_string()),
+        release_date(album),
+        album.release_type.map(|kind| kind.to_string()),
+        album.label.clone(),
+        album.catalog.clone(),
+        Some(album.tags.join("; ")),
+        album.album_art_url.clone(),
+        Some(track.num.to_string()),
+        Some(track.name.clone()),
+        Some(track.artist_name(album).to_owned()),
+        track
+            .duration
+            .map(|duration| duration.as_secs_f64().to_string()),
+        Some(track.url.clone()),
+        track.page_url.clone(),
+        track.id.map(|id| id.to_string()),
+    ]
+}
+
+/// Release date as `YYYY-MM-DD`, or as found on the page when it couldn't be parsed.
+fn release_date(album: &Album) -> Option<String> {
+    match album.released {
+        Some(date) => Some(date.date().to_string()),
+        None if album.release_date.is_empty() => None,
+        None => Some(album.release_date.clone()),
+    }
+}
+
+/// Print `albums` available for download in the `output` format,
+/// tables also tell which tracks are already downloaded under `path`.
+pub fn print_albums(
+    albums: &[Album],
+    output: Output,
+    path: Option<&PathBuf>,
+    track_format: &str,
+) -> Result<()> {
+    let Some(format) = Records::of(output) else {
+        print_as_tree(albums, path, track_format);
+        return Ok(());
+    };
+
+    print_records(
+        format,
+        albums,
+        LIST_COLUMNS,
+        albums.iter().flat_map(|album| {
+            album
+                .tracks
+                .iter()
+                .map(move |track| track_fields(album, track))
+        }),
+    )
+}
+
+fn print_table(results: &[SearchResult], numbered: bool) {
+    use term_table::{
+        row::Row,
+        table_cell::{Alignment, TableCell},
+        Table, TableStyle,
+    };
+
+    let cell = |text: &str| TableCell::new_with_alignment(text, 1, Alignment::Left);
+    let header = |text: &str| {
+        TableCell::new_with_alignment(style(text).bold().underlined(), 1, Alignment::Left)
+    };
+
+    let kinds = [
+        (SearchKind::Artist, "Artists"),
+        (SearchKind::Label, "Labels"),
+        (SearchKind::Album, "Albums"),
+        (SearchKind::Track, "Tracks"),
+    ];
+
+    // one table per kind, as each kind has its own columns
+    let tables = kinds
+        .into_iter()
+        .filter_map(|(kind, title)| {
+            // numbers are positions in `results`, which are grouped by kind already
+            let items = results
+                .iter()
+                .enumerate()
+                .filter(|(_, item)| item.kind == kind);
+
+            let mut table = Table::new();
+
+            table.style = TableStyle::blank();
+
+            table.separate_rows = false;
+
+            let columns = match kind {
+                SearchKind::Artist | SearchKind::Label => ["Name", "Location", "Genre", "Url"],
+                SearchKind::Album => ["Name", "Artist", "Genre", "Url"],
+                SearchKind::Track => ["Name", "Artist", "Album", "Url"],
+            };
+
+            let mut row = numbered
+                .then(|| header("#"))
+                .into_iter()
+                .collect::<Vec<_>>();
+            row.extend(columns.map(header));
+            table.add_row(Row::new(row));
+
+            for (num, item) in items {
+                let (second, third) = match kind {
+                    SearchKind::Artist | SearchKind::Label => (&item.location, &item.genre),
+                    SearchKind::Album => (&item.band_name, &item.genre),
+                    SearchKind::Track => (&item.band_name, &item.album_name),
+                };
+
+                let mut row = numbered
+                    .then(|| cell(&(num + 1).to_string()))
+                    .into_iter()
+                    .collect::<Vec<_>>();
+
+                row.extend([
+                    cell(&item.name),
+                    cell(second.as_deref().unwrap_or_default()),
+                    cell(third.as_deref().unwrap_or_default()),
+                    cell(&item.url),
+                ]);
+
+                table.add_row(Row::new(row));
+            }
+
+            (table.rows.len() > 1).then_some((title, table))
+        })
+        .collect::<Vec<_>>();
+
+    let titled = tables.len() > 1;
+
+    for (i, (title, table)) in tables.iter().enumerate() {
+        if titled {
+            if i > 0 {
+                println!();
+            }
+
+            println!("{}", style(title).bold());
+        }
+
+        println!("{}", table.render().trim_end());
+    }
+}
+
+/// Ask which of `count` numbered results to download, `None` if none is picked.
+pub fn prompt_pick(count: usize) -> Result<Option<usize>> {
+    let stdin = io::stdin();
+
+    loop {
+        eprint!("Download which one? [1-{count}, empty to quit]: ");
+        io::stderr().flush()?;
+
+        let mut answer = String::new();
+
+        if stdin.read_line(&mut answer)? == 0 {
+            return Ok(None);
+        }
+
+        let answer = answer.trim();
+
+        if answer.is_empty() {
+            return Ok(None);
+        }
+
+        match answer.parse::<usize>() {
+            Ok(num) if (1..=count).contains(&num) => return Ok(Some(num - 1)),
+            _ => eprintln!("Expected a number between 1 and {count}"),
+        }
+    }
+}
+
+/// Print `albums` grouped by artist, marking tracks already downloaded under `path`
+/// (as per [`sunny::utils::make_path`]) and tracks that can't be downloaded at all.
+pub fn print_as_tree(albums: &[Album], path: Option<&PathBuf>, track_format: &str) {
+    if albums.is_empty() {
+        println!("Noting to print");
+        return;
+    }
+
+    // label pages list albums of many artists, keep them in the order they first appear
+    let mut artists = Vec::<(&str, Vec<&Album>)>::new();
+
+    for album in albums {
+        match artists
+            .iter_mut()
+            .find(|(artist, _)| *artist == album.artist)
+        {
+            Some((_, albums)) => albums.push(album),
+            None => artists.push((&album.artist, vec![album])),
+        }
+    }
+
+    let (mut total, mut downloaded, mut unavailable) = (0, 0, 0);
+
+    for (artist, albums) in artists {
+        println!("{}", style(artist).bold());
+
+        for (album_index, album) in albums.iter().enumerate() {
+            let last_album = album_index + 1 == albums.len();
+            let root = album_directory(path, album);
+
+            let details = [
+                album.released.map(|date| date.date().to_string()),
+                album.release_type.map(|kind| kind.to_string()),
+                album.label.clone(),
+                album.catalog.clone(),
+                album.duration().map(format_duration),
+            ]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+
+            let padding = if last_album { "└──" } else { "├──" };
+
+            if details.is_empty() {
+                println!("{padding} {}", album.album);
+            } else {
+                println!("{padding} {} ({})", album.album, details.join(", "));
+            }
+
+            for (track_index, track) in album.tracks.iter().enumerate() {
+                let bar = if last_album { " " } else { "│" };
+                let padding = if track_index + 1 == album.tracks.len() {
+                    "└──"
+                } else {
+                    "├──"
+                };
+
+                let mut line = format!("{bar}   {padding} {:02}. {}", track.num, track.name);
+
+                if let Some(track_artist) = &track.artist {
+                    line.push_str(&format!(" - {track_artist}"));
+                }
+
+                if let Some(duration) = track.duration {
+                    line.push_str(&format!(" ({})", format_duration(duration)));
+                }
+
+                total += 1;
+
+                if make_path(album, track, &root, track_format).exists() {
+                    downloaded += 1;
+                    line.push_str(&format!(" {}", style("✔").green()));
+                } else if track.url.is_empty() {
+                    unavailable += 1;
+                    line.push_str(&format!(" {}", style("✘ not streamable").red()));
+                }
+
+                println!("{line}");
+            }
+        }
+    }
+
+    println!(
+        "\n{downloaded} of {total} tracks downloaded, {unavailable} not streamable, {} missing",
+        total - downloaded - unavailable
+    );
+}
@@ -0,0 +1,181 @@
+use std::{collections::HashMap, path::PathBuf, sync::Mutex};
+
+use console::style;
+#[cfg(feature = "progress")]
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use sunny::{
+    events::{Event, Skip},
+    models::{Album, Track},
+};
+
+/// `artist/album/track`, identifying a download in messages.
+fn prefix(album: &Album, track: &Track) -> String {
+    PathBuf::from(&album.artist)
+        .join(&album.album)
+        .join(&track.name)
+        .display()
+        .to_string()
+}
+
+fn skip_message(track: &Track, reason: Skip) -> String {
+    match reason {
+        Skip::NoStreamUrl => format!("No url found for `{}`, skipping.", track.name),
+        Skip::Exists => format!("`{}` already exists, skipping", track.name),
+        Skip::Intact => format!("`{}` already exists and looks intact, skipping", track.name),
+    }
+}
+
+/// Renders [`Event`]s as a spinner while fetching and a progress bar per download.
+#[cfg(feature = "progress")]
+#[derive(Default)]
+pub struct Progress {
+    meter: MultiProgress,
+    spinner: Mutex<Option<ProgressBar>>,
+    /// Bar & stream url of each download in progress
+    downloads: Mutex<HashMap<usize, (ProgressBar, String)>>,
+}
+
+#[cfg(feature = "progress")]
+impl Progress {
+    pub fn handle(&self, event: &Event) {
+        match event {
+            Event::FetchStarted { .. } => {
+                let pb = ProgressBar::new_spinner().with_style(
+                    ProgressStyle::default_spinner()
+                        .template("{spinner} {prefix} {msg} ({elapsed})")
+                        .expect("valid template"),
+                );
+                pb.enable_steady_tick(std::time::Duration::from_millis(100));
+                pb.set_prefix("Fetching artist's info");
+
+                *self.spinner.lock().unwrap() = Some(pb);
+            }
+            Event::FetchFinished { .. } => self.finish_spinner(style("✔").bold().green()),
+            Event::FetchFailed { .. } => self.finish_spinner(style("✘").bold().red()),
+            Event::Skipped { track, reason, .. } => {
+                let line = skip_message(track, *reason);
+
+                self.meter.suspend(|| eprintln!("{line}"));
+            }
+            Event::Queued {
+                id, album, track, ..
+            } => {
+                let pb = self.meter.add(
+                    ProgressBar::new(0).with_style(
+                        ProgressStyle::with_template(
+                            "{prefix} {msg}\n[{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})",
+                        )
+                        .expect("valid template")
+                        .progress_chars("=> "),
+                    ),
+                );
+
+                pb.set_prefix(prefix(album, track));
+
+                self.downloads
+                    .lock()
+                    .unwrap()
+                    .insert(*id, (pb, track.url.clone()));
+            }
+            Event::Progress {
+                id,
+                downloaded,
+                total,
+            } => self.with_bar(*id, |pb, _| {
+                pb.set_length(*total);
+                pb.set_position(*downloaded);
+            }),
+            Event::Retrying { id, error, .. } => self.with_bar(*id, |pb, _| {
+                pb.println(format!("{} {error}, retrying", pb.prefix()));
+                pb.reset();
+            }),
+            Event::Tagging { id } => self.with_bar(*id, |pb, _| pb.set_message("💾")),
+            Event::Completed { id } => {
+                if let Some((pb, _)) = self.downloads.lock().unwrap().remove(id) {
+                    pb.println(format!("{} {}", pb.prefix(), style("✔").green()));
+                    pb.finish_and_clear();
+                    self.meter.remove(&pb);
+                }
+            }
+            Event::Failed { id, error } => {
+                if let Some((pb, url)) = self.downloads.lock().unwrap().remove(id) {
+                    pb.finish_and_clear();
+                    self.meter.remove(&pb);
+                    self.meter.suspend(|| println!("E: {error:#} - <{url}>"));
+                }
+            }
+            Event::ReplayGainFailed { id, error } => self.with_bar(*id, |pb, _| {
+                let _ = self
+                    .meter
+                    .println(format!("{} ReplayGain: {error}", pb.prefix()));
+            }),
+            Event::AlbumGainFailed { directory, error } => {
+                let line = format!("{} ReplayGain: {error:#}", directory.display());
+
+                self.meter.suspend(|| eprintln!("{line}"));
+            }
+            _ => {}
+        }
+    }
+
+    fn finish_spinner(&self, message: impl ToString) {
+        if let Some(pb) = self.spinner.lock().unwrap().take() {
+            pb.finish_with_message(message.to_string());
+        }
+    }
+
+    fn with_bar(&self, id: usize, f: impl FnOnce(&ProgressBar, &str)) {
+        if let Some((pb, url)) = self.downloads.lock().unwrap().get(&id) {
+            f(pb, url);
+        }
+    }
+}
+
+/// Renders [`Event`]s as plain lines, for builds without progress bars.
+#[cfg(not(feature = "progress"))]
+#[derive(Default)]
+pub struct Progress {
+    /// Prefix & stream url of each download in progress
+    downloads: Mutex<HashMap<usize, (String, String)>>,
+}
+
+#[cfg(not(feature = "progress"))]
+impl Progress {
+    pub fn handle(&self, event: &Event) {
+        let downloads = || self.downloads.lock().unwrap();
+
+        match event {
+            Event::FetchStarted { .. } => eprintln!("Fetching artist's info"),
+            Event::Skipped { track, reason, .. } => eprintln!("{}", skip_message(track, *reason)),
+            Event::Queued {
+                id, album, track, ..
+            } => {
+                downloads().insert(*id, (prefix(album, track), track.url.clone()));
+            }
+            Event::Retrying { id, error, .. } => {
+                if let Some((prefix, _)) = downloads().get(id) {
+                    eprintln!("{prefix} {error}, retrying");
+                }
+            }
+            Event::Completed { id } => {
+                if let Some((prefix, _)) = downloads().remove(id) {
+                    println!("{prefix} {}", style("✔").green());
+                }
+            }
+            Event::Failed { id, error } => {
+                if let Some((_, url)) = downloads().remove(id) {
+                    println!("E: {error:#} - <{url}>");
+                }
+            }
+            Event::ReplayGainFailed { id, error } => {
+                if let Some((prefix, _)) = downloads().get(id) {
+                    eprintln!("{prefix} ReplayGain: {error}");
+                }
+            }
+            Event::AlbumGainFailed { directory, error } => {
+                eprintln!("{} ReplayGain: {error:#}", directory.display());
+            }
+            _ => {}
+        }
+    }
+}
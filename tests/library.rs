@@ -0,0 +1,126 @@
+#![cfg(feature = "tagging")]
+
+use std::fs;
+
+use id3::{Tag, TagLike, Version};
+use sunny::library;
+
+fn tagged_file(path: &std::path::Path, num: u32, title: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, b"").unwrap();
+
+    let mut tag = Tag::new();
+    tag.set_title(title);
+    tag.set_track(num);
+    tag.set_album("Album");
+    tag.set_album_artist("Artist");
+    tag.write_to_path(path, Version::Id3v24).unwrap();
+}
+
+#[test]
+fn reorganize_moves_and_cleans_up() {
+    let root = std::env::temp_dir().join("sunny-reorganize");
+    let _ = fs::remove_dir_all(&root);
+
+    tagged_file(&root.join("old/dir/a.mp3"), 1, "One");
+    tagged_file(&root.join("old/b.mp3"), 2, "Two");
+    tagged_file(&root.join("old/c.mp3"), 2, "Two");
+
+    let moves = library::plan_reorganize(&root, "{track} ({num})").unwrap();
+
+    assert_eq!(moves.len(), 3);
+    assert_eq!(
+        moves.iter().filter(|item| item.conflict.is_some()).count(),
+        2
+    );
+
+    library::reorganize(&moves, &root);
+
+    assert!(root.join("Artist/Album/One (1).mp3").exists());
+    assert!(!root.join("old/dir").exists());
+    assert!(root.join("old/b.mp3").exists());
+}
+
+#[test]
+fn reorganize_checks_lyrics_files() {
+    let root = std::env::temp_dir().join("sunny-reorganize-lyrics");
+    let _ = fs::remove_dir_all(&root);
+
+    tagged_file(&root.join("old/a.mp3"), 1, "One");
+    fs::write(root.join("old/a.lrc"), "lyrics").unwrap();
+    fs::create_dir_all(root.join("Artist/Album")).unwrap();
+    fs::write(root.join("Artist/Album/One (1).lrc"), "other lyrics").unwrap();
+
+    let moves = library::plan_reorganize(&root, "{track} ({num})").unwrap();
+
+    assert_eq!(moves.len(), 1);
+    assert!(moves[0].conflict.as_ref().unwrap().contains("One (1).lrc"));
+
+    fs::remove_file(root.join("Artist/Album/One (1).lrc")).unwrap();
+
+    let moves = library::plan_reorganize(&root, "{track} ({num})").unwrap();
+    let moved = library::reorganize(&moves, &root);
+
+    assert!(moved[0].track.is_ok());
+    assert!(moved[0].lyrics.is_empty());
+    assert!(root.join("Artist/Album/One (1).lrc").exists());
+}
+
+#[test]
+fn reorganize_keeps_destinations_created_since_planning() {
+    let root = std::env::temp_dir().join("sunny-reorganize-late");
+    let _ = fs::remove_dir_all(&root);
+
+    tagged_file(&root.join("old/a.mp3"), 1, "One");
+
+    let moves = library::plan_reorganize(&root, "{track} ({num})").unwrap();
+
+    fs::create_dir_all(root.join("Artist/Album")).unwrap();
+    fs::write(root.join("Artist/Album/One (1).mp3"), b"other").unwrap();
+
+    let moved = library::reorganize(&moves, &root);
+
+    assert!(moved[0].track.is_err());
+    assert!(root.join("old/a.mp3").exists());
+    assert_eq!(
+        fs::read(root.join("Artist/Album/One (1).mp3")).unwrap(),
+        b"other"
+    );
+}
+
+#[test]
+fn reorganize_reads_album_keys_back() {
+    use sunny::models::{Album, ReleaseType, Track};
+    use sunny::utils;
+
+    let root = std::env::temp_dir().join("sunny-reorganize-keys");
+    let _ = fs::remove_dir_all(&root);
+
+    let album = Album {
+        artist: "Artist".to_string(),
+        album: "Album".to_string(),
+        label: Some("Label".to_string()),
+        catalog: Some("LBL-001".to_string()),
+        release_type: Some(ReleaseType::Ep),
+        ..Default::default()
+    };
+    let track = Track {
+        num: 1,
+        name: "One".to_string(),
+        ..Default::default()
+    };
+
+    let from = root.join("old.mp3");
+    fs::create_dir_all(&root).unwrap();
+    fs::write(&from, b"").unwrap();
+    utils::tag_mp3(None, &album, &track, &from, &Default::default()).unwrap();
+
+    let moves =
+        library::plan_reorganize(&root, "[{catalog}] {track} ({release_type}, {label})").unwrap();
+
+    assert_eq!(moves.len(), 1);
+    assert_eq!(
+        moves[0].to,
+        root.join("Artist/Album/[LBL-001] One (ep, Label).mp3")
+    );
+}
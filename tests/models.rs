@@ -0,0 +1,45 @@
+use sunny::models::{Album, Track};
+
+fn album(id: u64, tracks: usize) -> Album {
+    let mut album = Album {
+        id: Some(id),
+        tracks: (1..=tracks)
+            .map(|num| Track {
+                num: num as i32,
+                name: format!("Track {num}"),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    };
+
+    album.link_tracks();
+    album
+}
+
+#[test]
+fn track_reaches_album_and_siblings() {
+    let albums = [album(1, 2), album(2, 3)];
+
+    let track = &albums[1].tracks[0];
+
+    assert_eq!(track.album_id, Some(2));
+    assert!(std::ptr::eq(track.album(&albums).unwrap(), &albums[1]));
+
+    let siblings = track
+        .siblings(&albums)
+        .map(|track| track.name.as_str())
+        .collect::<Vec<_>>();
+
+    assert_eq!(siblings, ["Track 2", "Track 3"]);
+
+    // a copy of the track is looked up by its album's ID
+    let copy = track.clone();
+
+    assert!(std::ptr::eq(copy.album(&albums).unwrap(), &albums[1]));
+    assert_eq!(copy.siblings(&albums).count(), 2);
+
+    let orphan = Track::default();
+
+    assert!(orphan.album(&albums).is_none());
+}
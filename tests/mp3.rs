@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+use sunny::mp3;
+
+/// `count` silent MPEG-1 layer III frames, 128 kbps at 44.1 kHz
+fn frames(count: usize) -> Vec<u8> {
+    let mut frame = vec![0u8; 417];
+    frame[..4].copy_from_slice(&[0xff, 0xfb, 0x90, 0x00]);
+
+    frame.repeat(count)
+}
+
+#[test]
+fn parse_frames() {
+    let stream = mp3::parse(&frames(100)).unwrap();
+
+    assert_eq!(stream.frames, 100);
+    assert_eq!(stream.duration.as_millis(), 2612);
+}
+
+#[test]
+fn parse_truncated() {
+    let mut data = frames(100);
+    data.truncate(data.len() - 100);
+
+    assert!(mp3::parse(&data).is_err());
+}
+
+#[test]
+fn parse_garbage() {
+    assert!(mp3::parse(b"<html>Not found</html>").is_err());
+    assert!(mp3::parse(b"").is_err());
+}
+
+#[test]
+fn verify_duration() {
+    let data = frames(1000);
+
+    assert!(mp3::verify(&data, Some(Duration::from_secs(26))).is_ok());
+    assert!(mp3::verify(&data, Some(Duration::from_secs(200))).is_err());
+}
@@ -0,0 +1,87 @@
+use sunny::{
+    models::{Album, Track},
+    overrides::Overrides,
+    utils,
+};
+
+fn album() -> Album {
+    let mut album = Album {
+        artist: "artist".to_string(),
+        album: "Album (Remastered 2020)".to_string(),
+        url: "https://artist.bandcamp.com/album/album".to_string(),
+        id: Some(42),
+        tags: vec!["electronic".to_string()],
+        ..Default::default()
+    };
+
+    album.tracks = (1..=2)
+        .map(|num| Track {
+            num,
+            name: format!("track {num}"),
+            ..Default::default()
+        })
+        .collect();
+
+    album
+}
+
+#[test]
+fn apply_by_url() {
+    let overrides = Overrides::parse(
+        r#"{
+            "https://artist.bandcamp.com/album/album/": {
+                "album": "Album",
+                "artist": "Artist",
+                "genres": ["Ambient"],
+                "date": "2020-01-31",
+                "tracks": { "2": { "name": "Track", "artist": "Someone" } }
+            }
+        }"#,
+    )
+    .unwrap();
+
+    let mut album = album();
+    overrides.apply(&mut album);
+
+    assert_eq!(album.album, "Album");
+    assert_eq!(album.tags, ["Ambient"]);
+    assert_eq!(
+        album.released.map(|date| date.to_string()).as_deref(),
+        Some("2020-01-31 00:00:00")
+    );
+
+    let track = &album.tracks[1];
+
+    assert_eq!(
+        utils::parse_track_template(
+            "{artist}/{album}/{num} - {track_artist} - {track}",
+            &album,
+            track
+        ),
+        "Artist/Album/2 - Someone - Track"
+    );
+    assert_eq!(album.tracks[0].name, "track 1");
+}
+
+#[test]
+fn apply_by_id() {
+    let overrides = Overrides::parse(r#"{ "42": { "album": "Album" } }"#).unwrap();
+
+    let mut album = album();
+    overrides.apply(&mut album);
+
+    assert_eq!(album.album, "Album");
+    assert_eq!(album.artist, "artist");
+}
+
+#[test]
+fn invalid() {
+    for json in [
+        "[]",
+        r#"{ "42": { "album": 1 } }"#,
+        r#"{ "42": { "date": "someday" } }"#,
+        r#"{ "42": { "tracks": { "one": {} } } }"#,
+    ] {
+        assert!(Overrides::parse(json).is_err(), "{json}");
+    }
+}
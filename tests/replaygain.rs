@@ -0,0 +1,59 @@
+#![cfg(feature = "tagging")]
+
+use sunny::replaygain;
+
+/// `secs` of a stereo 997 Hz sine wave at 48 kHz
+fn sine(amplitude: f32, secs: usize) -> Vec<f32> {
+    (0..48000 * secs)
+        .flat_map(|n| {
+            let sample =
+                amplitude * (2.0 * std::f32::consts::PI * 997.0 * n as f32 / 48000.0).sin();
+            [sample, sample]
+        })
+        .collect()
+}
+
+#[test]
+fn full_scale_sine_loudness() {
+    let analysis = replaygain::analyze_samples(&sine(1.0, 5), 2, 48000);
+
+    // as per EBU Tech 3341, a 997 Hz stereo sine at N dBFS measures N LUFS
+    let loudness = analysis.loudness().unwrap();
+    assert!(loudness.abs() < 0.1, "{loudness}");
+
+    let gain = analysis.replay_gain();
+    assert!((gain.track_gain + 18.0).abs() < 0.1);
+    assert!((gain.track_peak - 1.0).abs() < 0.01);
+}
+
+#[test]
+fn silence_has_no_loudness() {
+    let analysis = replaygain::analyze_samples(&[0.0; 96000], 2, 48000);
+
+    assert!(analysis.loudness().is_none());
+    assert_eq!(analysis.replay_gain().track_gain, 0.0);
+}
+
+#[test]
+fn album_gain() {
+    let loud = replaygain::analyze_samples(&sine(1.0, 5), 2, 48000);
+    let quiet = replaygain::analyze_samples(&sine(0.1, 5), 2, 48000);
+
+    let gains = replaygain::album(&[loud, quiet]);
+
+    assert_eq!(gains.len(), 2);
+    assert_eq!(gains[0].album_gain, gains[1].album_gain);
+    assert!(gains[1].track_gain > gains[0].track_gain);
+    assert_eq!(gains[1].album_peak, Some(gains[0].track_peak));
+}
+
+#[test]
+fn analyze_mp3() {
+    // silent MPEG-1 layer III frames, 128 kbps at 44.1 kHz
+    let mut frame = vec![0u8; 417];
+    frame[..4].copy_from_slice(&[0xff, 0xfb, 0x90, 0x00]);
+
+    let analysis = replaygain::analyze(&frame.repeat(100)).unwrap();
+
+    assert!(analysis.loudness().is_none());
+}
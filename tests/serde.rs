@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use sunny::{
+    models::{Album, ReleaseType, Track},
+    utils,
+};
+
+#[test]
+fn album_roundtrip() {
+    let mut album = Album {
+        artist: "Artist".to_string(),
+        album: "Album".to_string(),
+        release_date: "28 Sep 2014 00:00:00 GMT".to_string(),
+        released: utils::parse_date("28 Sep 2014 00:00:00 GMT"),
+        release_type: Some(ReleaseType::Ep),
+        tags: vec!["ambient".to_string()],
+        id: Some(42),
+        ..Default::default()
+    };
+
+    album.tracks = vec![Track {
+        num: 1,
+        name: "Track".to_string(),
+        url: "https://example.com/track.mp3".to_string(),
+        duration: Some(Duration::from_millis(201_500)),
+        ..Default::default()
+    }];
+
+    let json = serde_json::to_value(&album).unwrap();
+
+    assert_eq!(json["released"], "2014-09-28T00:00:00");
+    assert_eq!(json["release_type"], "ep");
+    assert_eq!(json["tracks"][0]["duration"], 201.5);
+
+    let parsed: Album = serde_json::from_value(json).unwrap();
+
+    assert_eq!(parsed.released, album.released);
+    assert_eq!(parsed.tracks[0].duration, album.tracks[0].duration);
+    assert_eq!(
+        utils::parse_track_template("{artist} - {track}", &parsed, &parsed.tracks[0]),
+        "Artist - Track"
+    );
+}
+
+#[test]
+fn missing_fields_default() {
+    let album: Album = serde_json::from_str(r#"{ "album": "Album" }"#).unwrap();
+
+    assert_eq!(album.album, "Album");
+    assert!(album.tracks.is_empty());
+}
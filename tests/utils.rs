@@ -1,5 +1,6 @@
 use sunny::utils;
 
+#[cfg(feature = "tagging")]
 #[test]
 fn timestamp_case1() {
     let ret = utils::timestamp("28 Sep 2014 04:19:31 GMT");
@@ -7,9 +8,373 @@ fn timestamp_case1() {
     assert!(ret.is_some());
 }
 
+#[cfg(feature = "tagging")]
 #[test]
 fn timestamp_case2() {
     let ret = utils::timestamp("released September 28, 2014");
 
     assert!(ret.is_some());
 }
+
+fn existing_track(
+    dir: &str,
+) -> (
+    sunny::models::Album,
+    sunny::models::Track,
+    std::path::PathBuf,
+) {
+    // each test passes its own `dir`, emptied so earlier runs don't leave files behind
+    let root = std::env::temp_dir().join(dir);
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+
+    let track = sunny::models::Track {
+        num: 1,
+        name: "Track".to_string(),
+        ..Default::default()
+    };
+
+    let album = sunny::models::Album::default();
+
+    std::fs::write(utils::make_path(&album, &track, &root, ""), b"").unwrap();
+
+    (album, track, root)
+}
+
+#[test]
+fn track_path_skip_existing() {
+    let (album, track, root) = existing_track("sunny-track-path-skip");
+
+    let ret = utils::track_path(&album, &track, &root, "", utils::OnExists::Skip);
+
+    assert!(ret.is_none());
+}
+
+#[test]
+fn track_path_rename_existing() {
+    let (album, track, root) = existing_track("sunny-track-path-rename");
+
+    let ret = utils::track_path(&album, &track, &root, "", utils::OnExists::Rename);
+
+    assert_eq!(ret, Some(root.join("1 - Track (2).mp3")));
+}
+
+#[test]
+fn track_path_verify_empty_file() {
+    let (album, track, root) = existing_track("sunny-track-path-verify");
+
+    let ret = utils::track_path(&album, &track, &root, "", utils::OnExists::Verify);
+
+    assert_eq!(ret, Some(root.join("1 - Track.mp3")));
+}
+
+#[cfg(feature = "tagging")]
+#[test]
+fn tag_mp3_preserves_foreign_frames() {
+    use id3::{Tag, TagLike, Version};
+
+    let (album, track, root) = existing_track("sunny-tag-mp3-preserve");
+    let path = utils::make_path(&album, &track, &root, "");
+
+    let mut tag = Tag::new();
+    tag.set_title("Old title");
+    tag.add_frame(id3::frame::Comment {
+        lang: "eng".to_string(),
+        description: "rating".to_string(),
+        text: "5".to_string(),
+    });
+    tag.add_frame(id3::frame::Lyrics {
+        lang: "eng".to_string(),
+        description: "translation".to_string(),
+        text: "paroles".to_string(),
+    });
+    tag.add_frame(id3::frame::Lyrics {
+        lang: "eng".to_string(),
+        description: String::new(),
+        text: "old lyrics".to_string(),
+    });
+    tag.add_frame(id3::frame::Picture {
+        mime_type: "image/jpeg".to_string(),
+        picture_type: id3::frame::PictureType::CoverFront,
+        description: String::new(),
+        data: vec![0xff, 0xd8],
+    });
+    tag.write_to_path(&path, Version::Id3v24).unwrap();
+
+    // the cover could not be fetched
+    utils::tag_mp3(None, &album, &track, &path, &Default::default()).unwrap();
+
+    let tag = Tag::read_from_path(&path).unwrap();
+
+    assert_eq!(tag.title(), Some("Track"));
+    assert!(tag
+        .comments()
+        .any(|comment| comment.description == "rating"));
+    assert_eq!(
+        tag.lyrics().map(|lyrics| &*lyrics.text).collect::<Vec<_>>(),
+        ["paroles"]
+    );
+    assert_eq!(tag.pictures().count(), 1);
+}
+
+#[cfg(feature = "tagging")]
+#[test]
+fn tag_mp3_writes_bandcamp_metadata() {
+    use id3::{Tag, TagLike};
+
+    let (mut album, mut track, root) = existing_track("sunny-tag-mp3-metadata");
+    let path = utils::make_path(&album, &track, &root, "");
+
+    track.id = Some(42);
+    album.num_tracks = 9;
+    album.url = "https://artist.bandcamp.com/album/album".to_string();
+    album.label = Some("Label".to_string());
+
+    utils::tag_mp3(None, &album, &track, &path, &Default::default()).unwrap();
+
+    let tag = Tag::read_from_path(&path).unwrap();
+
+    assert_eq!(tag.total_tracks(), Some(9));
+    assert_eq!(
+        tag.get("TPUB").and_then(|frame| frame.content().text()),
+        Some("Label")
+    );
+    assert!(tag.get("WOAS").is_some());
+    assert!(tag
+        .extended_texts()
+        .any(|txxx| txxx.description == "BANDCAMP_TRACK_ID" && txxx.value == "42"));
+}
+
+#[cfg(feature = "tagging")]
+#[test]
+fn tag_profile_art_url() {
+    let url = "https://f4.bcbits.com/img/a0123456789_10.jpg";
+
+    let profile = utils::TagProfile {
+        max_art_size: Some(500),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        profile.art_url(url).as_deref(),
+        Some("https://f4.bcbits.com/img/a0123456789_2.jpg")
+    );
+
+    let profile = utils::TagProfile {
+        pictures: false,
+        ..Default::default()
+    };
+
+    assert!(profile.art_url(url).is_none());
+}
+
+#[cfg(feature = "tagging")]
+#[test]
+fn tag_mp3_id3v23_with_id3v1() {
+    use id3::{Tag, TagLike, Version};
+
+    let (mut album, track, root) = existing_track("sunny-tag-mp3-id3v1");
+    let path = utils::make_path(&album, &track, &root, "");
+
+    album.released = utils::parse_date("2014-09-28T04:19:31Z");
+
+    let profile = utils::TagProfile {
+        version: Version::Id3v23,
+        id3v1: true,
+        ..Default::default()
+    };
+
+    utils::tag_mp3(None, &album, &track, &path, &profile).unwrap();
+    // retagging must not stack ID3v1 tags
+    utils::tag_mp3(None, &album, &track, &path, &profile).unwrap();
+
+    let bytes = std::fs::read(&path).unwrap();
+
+    assert_eq!(&bytes[3], &3);
+    assert_eq!(&bytes[bytes.len() - 128..bytes.len() - 125], b"TAG");
+    assert_eq!(bytes.windows(3).filter(|w| w == b"TAG").count(), 1);
+    assert_eq!(&bytes[bytes.len() - 35..bytes.len() - 31], b"2014");
+
+    let tag = Tag::read_from_path(&path).unwrap();
+
+    assert_eq!(tag.version(), Version::Id3v23);
+    assert_eq!(tag.year(), Some(2014));
+    assert_eq!(
+        tag.get("TDAT").and_then(|f| f.content().text()),
+        Some("2809")
+    );
+    assert_eq!(
+        tag.get("TIME").and_then(|f| f.content().text()),
+        Some("0419")
+    );
+    assert!(tag.get("TDRC").is_none() && tag.get("TDRL").is_none());
+}
+
+#[cfg(feature = "tagging")]
+#[test]
+fn write_lyrics_file_lrc() {
+    let (album, mut track, root) = existing_track("sunny-lyrics-file");
+    let path = utils::make_path(&album, &track, &root, "");
+
+    track.lyrics = Some("first line\nsecond line".to_string());
+
+    let profile = utils::TagProfile {
+        lyrics_file: Some(utils::LyricsFile::Lrc),
+        ..Default::default()
+    };
+
+    let file = utils::write_lyrics_file(&album, &track, &path, &profile).unwrap();
+
+    assert_eq!(file, Some(root.join("1 - Track.lrc")));
+
+    let content = std::fs::read_to_string(root.join("1 - Track.lrc")).unwrap();
+
+    assert!(content.starts_with("[ti:Track]\n"));
+    assert!(content.ends_with("first line\nsecond line\n"));
+}
+
+#[test]
+fn parse_duration_iso8601() {
+    use std::time::Duration;
+
+    assert_eq!(
+        utils::parse_duration("P00H03M21S"),
+        Some(Duration::from_secs(201))
+    );
+    assert_eq!(
+        utils::parse_duration("PT1H0M1.5S"),
+        Some(Duration::from_secs_f64(3601.5))
+    );
+    assert_eq!(utils::parse_duration("03:21"), None);
+    assert_eq!(utils::parse_duration("P"), None);
+    assert_eq!(utils::parse_duration("P99999999999999999999999D"), None);
+}
+
+#[test]
+fn format_duration_case() {
+    use std::time::Duration;
+
+    assert_eq!(utils::format_duration(Duration::from_secs(201)), "3:21");
+    assert_eq!(utils::format_duration(Duration::from_secs(3601)), "1:00:01");
+}
+
+#[cfg(feature = "tagging")]
+#[test]
+fn track_artist_on_compilations() {
+    use id3::{Tag, TagLike};
+
+    let (mut album, mut track, root) = existing_track("sunny-track-artist");
+    let path = utils::make_path(&album, &track, &root, "");
+
+    album.artist = "Various Artists".to_string();
+    track.artist = Some("Someone".to_string());
+
+    assert_eq!(
+        utils::parse_track_template("{track_artist} - {track} ({artist})", &album, &track),
+        "Someone - Track (Various Artists)"
+    );
+
+    utils::tag_mp3(None, &album, &track, &path, &Default::default()).unwrap();
+
+    let tag = Tag::read_from_path(&path).unwrap();
+
+    assert_eq!(tag.artist(), Some("Someone"));
+    assert_eq!(tag.album_artist(), Some("Various Artists"));
+}
+
+#[cfg(feature = "tagging")]
+#[test]
+fn label_and_release_type() {
+    use id3::{Tag, TagLike};
+    use sunny::models::ReleaseType;
+
+    let (mut album, track, root) = existing_track("sunny-label");
+    let path = utils::make_path(&album, &track, &root, "");
+
+    album.label = Some("Label".to_string());
+    album.catalog = Some("LBL-001".to_string());
+    album.release_type = ReleaseType::from_schema("http://schema.org/EPRelease");
+
+    assert_eq!(
+        utils::parse_track_template(
+            "[{catalog}] {track} ({release_type}, {label})",
+            &album,
+            &track
+        ),
+        "[LBL-001] Track (ep, Label)"
+    );
+
+    utils::tag_mp3(None, &album, &track, &path, &Default::default()).unwrap();
+
+    let tag = Tag::read_from_path(&path).unwrap();
+
+    assert_eq!(
+        tag.get("TPUB").and_then(|frame| frame.content().text()),
+        Some("Label")
+    );
+}
+
+#[test]
+fn parse_date_formats() {
+    use chrono::NaiveDate;
+
+    let day = NaiveDate::from_ymd_opt(2014, 9, 28).unwrap();
+
+    for date in [
+        "28 Sep 2014 00:00:00 GMT",
+        "Sun, 28 Sep 2014 00:00:00 GMT",
+        "released September 28, 2014",
+        "releases September 28, 2014",
+        "September 28, 2014",
+        "2014-09-28",
+        "2014-09-28T00:00:00Z",
+        "2014-09-28T00:00:00.000+00:00",
+    ] {
+        assert_eq!(
+            utils::parse_date(date).map(|date| date.date()),
+            Some(day),
+            "{date}"
+        );
+    }
+
+    assert!(utils::parse_date("someday").is_none());
+}
+
+#[cfg(feature = "tagging")]
+#[test]
+fn timestamp_of_dates() {
+    let ts = utils::timestamp("28 Sep 2014 04:19:31 GMT").unwrap();
+    assert_eq!((ts.day, ts.hour, ts.second), (Some(28), Some(4), Some(31)));
+
+    let ts = utils::timestamp("released September 28, 2014").unwrap();
+    assert_eq!((ts.day, ts.hour), (Some(28), None));
+}
+
+#[cfg(feature = "tagging")]
+#[test]
+fn genres_strategy_and_map() {
+    use id3::{Tag, TagLike};
+
+    let (mut album, track, root) = existing_track("sunny-genres");
+    let path = utils::make_path(&album, &track, &root, "");
+
+    album.tags = ["Electronic", "ambient", "Drone", "chill"]
+        .map(String::from)
+        .to_vec();
+
+    let mut profile = utils::TagProfile::default();
+    assert_eq!(profile.genres(&album).len(), 4);
+
+    profile.genre_map =
+        Some(utils::GenreMap::parse("# comment\nambient = Ambient\n\ndrone\nelectronic").unwrap());
+    assert_eq!(profile.genres(&album), ["electronic", "Ambient", "drone"]);
+
+    utils::tag_mp3(None, &album, &track, &path, &profile).unwrap();
+    let tag = Tag::read_from_path(&path).unwrap();
+    assert_eq!(tag.genres(), Some(vec!["electronic", "Ambient", "drone"]));
+
+    profile.genre = utils::Genre::First;
+    assert_eq!(profile.genres(&album), ["electronic"]);
+
+    assert!(utils::GenreMap::parse("= Genre").is_err());
+}